@@ -0,0 +1,73 @@
+//! Contention benchmark for `ProgressBar::tick`.
+//!
+//! Many threads tick one shared bar, as rayon workers sharing a clone do.
+//! The `mutex` column takes a shared mutex around every tick, so ticks run
+//! one at a time and each one draws, for comparison with the same ticks left
+//! to contend on the bar itself. It shows what threads gain from not waiting
+//! on each other; it is not a measurement of earlier versions of the crate.
+//!
+//! Run with `cargo bench`.
+
+use nanoprogress::{DrawTarget, ProgressBar};
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICKS_PER_THREAD: u64 = 200_000;
+const THREADS: [usize; 4] = [1, 4, 16, 32];
+
+/// Run `tick` `TICKS_PER_THREAD` times on each of `threads` threads and
+/// return the mean wall-clock time per tick.
+fn measure(threads: usize, tick: impl Fn() + Sync) -> Duration {
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..TICKS_PER_THREAD {
+                    tick();
+                }
+            });
+        }
+    });
+    start.elapsed() / (threads as u64 * TICKS_PER_THREAD) as u32
+}
+
+fn bar(redraws_per_sec: u32) -> ProgressBar {
+    ProgressBar::new(u64::MAX)
+        .draw_target(DrawTarget::Writer(Box::new(io::sink())))
+        .tty(true)
+        .max_redraws_per_sec(redraws_per_sec)
+        .start()
+}
+
+fn main() {
+    println!(
+        "{:>7}  {:>14}  {:>14}  {:>14}  {:>14}",
+        "threads", "mutex", "tick", "tick 20/s", "hidden"
+    );
+    for threads in THREADS {
+        let turn = Mutex::new(());
+        let serialized = bar(0);
+        let mutex = measure(threads, || {
+            let _turn = turn.lock().unwrap();
+            serialized.tick(1);
+        });
+        serialized.finish_and_clear();
+
+        let unthrottled = bar(0);
+        let every = measure(threads, || unthrottled.tick(1));
+        unthrottled.finish_and_clear();
+
+        let throttled = bar(20);
+        let ticked = measure(threads, || throttled.tick(1));
+        throttled.finish_and_clear();
+
+        let hidden = ProgressBar::hidden(u64::MAX);
+        let quiet = measure(threads, || hidden.tick(1));
+
+        println!(
+            "{threads:>7}  {mutex:>14.0?}  {every:>14.0?}  {ticked:>14.0?}  {quiet:>14.0?}"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use nanoprogress::{MultiProgress, ProgressBar};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let multi = MultiProgress::new();
+
+    let handles: Vec<_> = [(40, 30), (60, 20), (25, 60)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (total, delay))| {
+            let bar = multi.add(
+                ProgressBar::new(total)
+                    .width(25)
+                    .prefix(&format!("job {}", i + 1))
+                    .template("{prefix} [{bar}] {pos}/{len} {msg}")
+                    .unwrap()
+                    .message("working..."),
+            );
+            thread::spawn(move || {
+                for _ in 0..total {
+                    thread::sleep(Duration::from_millis(delay));
+                    bar.tick(1);
+                }
+                bar.success(&format!("job {} finished", i + 1));
+            })
+        })
+        .collect();
+
+    for h in handles {
+        h.join().unwrap();
+    }
+}
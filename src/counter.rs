@@ -0,0 +1,194 @@
+//! Position and length kept in atomics, so ticks from many threads don't
+//! contend on the state lock.
+
+use crate::estimate::Clock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Stored length of a spinner, which has none. Real lengths are at least 1.
+const NO_LENGTH: u64 = 0;
+
+/// The parts of a bar's state that ticks update without taking its lock.
+///
+/// The state under the lock keeps a copy of the position and length, taken
+/// whenever the lock is held, and draws from that copy so a line never mixes
+/// values from before and after a concurrent tick.
+pub(crate) struct Counter {
+    pos: AtomicU64,
+    len: AtomicU64,
+    finished: AtomicBool,
+    /// Number of `ProgressBar` handles. Unlike the state's reference count,
+    /// this isn't raised by a steady-tick thread borrowing the state.
+    handles: AtomicUsize,
+    /// Earliest time the bar may redraw, in nanoseconds since `origin`.
+    next_draw: AtomicU64,
+    /// A tick wants a redraw, and whoever gets the state lock next draws it.
+    pending: AtomicBool,
+    origin: Instant,
+    clock: Clock,
+}
+
+impl Counter {
+    pub(crate) fn new(len: Option<u64>, clock: Clock) -> Self {
+        Counter {
+            pos: AtomicU64::new(0),
+            len: AtomicU64::new(len.map_or(NO_LENGTH, |len| len.max(1))),
+            finished: AtomicBool::new(false),
+            handles: AtomicUsize::new(1),
+            next_draw: AtomicU64::new(0),
+            pending: AtomicBool::new(false),
+            origin: clock.now(),
+            clock,
+        }
+    }
+
+    pub(crate) fn position(&self) -> u64 {
+        self.pos.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn length(&self) -> Option<u64> {
+        Some(self.len.load(Ordering::Relaxed)).filter(|&len| len != NO_LENGTH)
+    }
+
+    /// Move the position with `f`, clamped to the length. Returns `false`
+    /// without moving once the bar is finished.
+    pub(crate) fn update(&self, f: impl Fn(u64) -> u64) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.pos
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pos| Some(f(pos)))
+            .ok();
+        // Clamp after moving, against the length as it is now: a concurrent
+        // `set_length` either sees the new position and clamps it itself, or
+        // stored its length before this load.
+        self.clamp(self.len.load(Ordering::SeqCst));
+        true
+    }
+
+    /// Set the length, normalizing 0 to 1, and clamp the position to it.
+    pub(crate) fn set_length(&self, len: u64) {
+        let len = len.max(1);
+        self.len.store(len, Ordering::SeqCst);
+        self.clamp(len);
+    }
+
+    fn clamp(&self, len: u64) {
+        if len != NO_LENGTH {
+            self.pos.fetch_min(len, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_handle(&self) {
+        self.handles.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a handle as dropped. Returns `true` for the last one.
+    pub(crate) fn drop_handle(&self) -> bool {
+        self.handles.fetch_sub(1, Ordering::AcqRel) == 1
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Whether a redraw may be due at `now`. A cheap check before trying the
+    /// lock; the state still applies the interval and non-TTY policy itself.
+    pub(crate) fn draw_due(&self, now: Instant) -> bool {
+        self.nanos(now) >= self.next_draw.load(Ordering::Relaxed)
+    }
+
+    /// Skip redraws until `at`, or for good with `None`.
+    pub(crate) fn next_draw_at(&self, at: Option<Instant>) {
+        let nanos = at.map_or(u64::MAX, |at| self.nanos(at));
+        self.next_draw.store(nanos, Ordering::Relaxed);
+    }
+
+    /// Ask for a redraw from whoever holds the state lock or takes it next.
+    pub(crate) fn request_draw(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn draw_requested(&self) -> bool {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Claim a requested redraw. Returns `false` if there's none.
+    pub(crate) fn take_draw_request(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+
+    fn nanos(&self, t: Instant) -> u64 {
+        let since = t.saturating_duration_since(self.origin);
+        u64::try_from(since.as_nanos()).unwrap_or(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_update_clamps_to_length() {
+        let counter = Counter::new(Some(10), Clock::default());
+        assert!(counter.update(|pos| pos.saturating_add(25)));
+        assert_eq!(counter.position(), 10);
+        counter.set_length(4);
+        assert_eq!(counter.position(), 4);
+        counter.set_length(0);
+        assert_eq!(counter.length(), Some(1));
+
+        let spinner = Counter::new(None, Clock::default());
+        spinner.update(|pos| pos + 25);
+        assert_eq!((spinner.position(), spinner.length()), (25, None));
+    }
+
+    #[test]
+    fn test_position_stays_within_concurrent_length() {
+        let counter = Counter::new(Some(1000), Clock::default());
+        std::thread::scope(|scope| {
+            scope.spawn(|| (0..1000).for_each(|len| counter.set_length(1000 - len)));
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        counter.update(|pos| pos + 1);
+                    }
+                });
+            }
+        });
+        assert_eq!(counter.length(), Some(1));
+        assert_eq!(counter.position(), 1);
+    }
+
+    #[test]
+    fn test_finished_counter_ignores_updates() {
+        let counter = Counter::new(Some(10), Clock::default());
+        counter.finish();
+        assert!(!counter.update(|pos| pos + 1));
+        assert_eq!(counter.position(), 0);
+    }
+
+    #[test]
+    fn test_draw_due() {
+        let (clock, manual) = Clock::manual();
+        let counter = Counter::new(Some(10), clock.clone());
+        assert!(counter.draw_due(clock.now()));
+        counter.next_draw_at(Some(clock.now() + Duration::from_millis(100)));
+        manual.advance(Duration::from_millis(99));
+        assert!(!counter.draw_due(clock.now()));
+        manual.advance(Duration::from_millis(1));
+        assert!(counter.draw_due(clock.now()));
+        counter.next_draw_at(None);
+        manual.advance(Duration::from_secs(3600));
+        assert!(!counter.draw_due(clock.now()));
+    }
+}
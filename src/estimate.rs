@@ -0,0 +1,171 @@
+//! Time source and throughput estimation for elapsed, ETA and rate fields.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Source of `Instant`s. The system clock in normal use; tests swap in a
+/// manually advanced clock so timing-dependent output is deterministic.
+#[derive(Clone, Default)]
+pub(crate) struct Clock(Option<Arc<dyn Fn() -> Instant + Send + Sync>>);
+
+impl Clock {
+    pub(crate) fn now(&self) -> Instant {
+        match &self.0 {
+            Some(f) => f(),
+            None => Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Clock {
+    /// A clock that only moves when the returned handle is advanced.
+    pub(crate) fn manual() -> (Clock, ManualClock) {
+        let handle = ManualClock {
+            base: Instant::now(),
+            offset: Arc::new(std::sync::Mutex::new(Duration::ZERO)),
+        };
+        let h = handle.clone();
+        (Clock(Some(Arc::new(move || h.now()))), handle)
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone)]
+pub(crate) struct ManualClock {
+    base: Instant,
+    offset: Arc<std::sync::Mutex<Duration>>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub(crate) fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+
+    fn now(&self) -> Instant {
+        self.base + *self.offset.lock().unwrap()
+    }
+}
+
+/// Samples closer together than this are merged, so bursts of ticks within
+/// the same instant don't produce infinite or wildly noisy rates.
+const MIN_SAMPLE: Duration = Duration::from_millis(50);
+
+/// Time for an old rate sample to lose half its weight in the average.
+const HALF_LIFE_SECS: f64 = 2.0;
+
+/// Exponentially weighted moving average of items per second.
+///
+/// The weight of each sample depends on how much time it covers rather than on
+/// the number of ticks, so irregular tick spacing doesn't skew the estimate.
+pub(crate) struct Estimator {
+    rate: Option<f64>,
+    last_pos: u64,
+    last_time: Instant,
+}
+
+impl Estimator {
+    pub(crate) fn new(now: Instant) -> Self {
+        Estimator {
+            rate: None,
+            last_pos: 0,
+            last_time: now,
+        }
+    }
+
+    pub(crate) fn record(&mut self, pos: u64, now: Instant) {
+        if pos < self.last_pos {
+            // Progress went backwards; restart the sample window but keep the rate.
+            self.last_pos = pos;
+            self.last_time = now;
+            return;
+        }
+        let dt = now.saturating_duration_since(self.last_time);
+        if dt < MIN_SAMPLE {
+            return;
+        }
+        let secs = dt.as_secs_f64();
+        let sample = (pos - self.last_pos) as f64 / secs;
+        self.rate = Some(match self.rate {
+            None => sample,
+            Some(rate) => {
+                let weight = 1.0 - 0.5f64.powf(secs / HALF_LIFE_SECS);
+                rate + weight * (sample - rate)
+            }
+        });
+        self.last_pos = pos;
+        self.last_time = now;
+    }
+
+    /// Smoothed items per second, falling back to the overall average until
+    /// the first full sample is in.
+    pub(crate) fn per_sec(&self, pos: u64, elapsed: Duration) -> f64 {
+        match self.rate {
+            Some(rate) => rate,
+            None if elapsed.is_zero() => 0.0,
+            None => pos as f64 / elapsed.as_secs_f64(),
+        }
+    }
+
+    /// Estimated time to reach `total` at the current rate, if there is one.
+    pub(crate) fn eta(&self, pos: u64, total: u64, elapsed: Duration) -> Option<Duration> {
+        let rate = self.per_sec(pos, elapsed);
+        if rate <= 0.0 || !rate.is_finite() {
+            return None;
+        }
+        let remaining = total.saturating_sub(pos) as f64 / rate;
+        Duration::try_from_secs_f64(remaining).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steady_rate() {
+        let start = Instant::now();
+        let mut est = Estimator::new(start);
+        for i in 1..=10 {
+            est.record(i * 5, start + Duration::from_secs(i));
+        }
+        let rate = est.per_sec(50, Duration::from_secs(10));
+        assert!((rate - 5.0).abs() < 1e-9, "rate was {rate}");
+        assert_eq!(
+            est.eta(50, 100, Duration::from_secs(10)),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_rate_follows_change() {
+        let start = Instant::now();
+        let mut est = Estimator::new(start);
+        est.record(10, start + Duration::from_secs(1));
+        for i in 2..=20 {
+            est.record(10 + (i - 1) * 100, start + Duration::from_secs(i));
+        }
+        let rate = est.per_sec(1910, Duration::from_secs(20));
+        assert!(rate > 95.0 && rate <= 100.0, "rate was {rate}");
+    }
+
+    #[test]
+    fn test_bursts_within_min_sample_are_merged() {
+        let start = Instant::now();
+        let mut est = Estimator::new(start);
+        est.record(1, start);
+        est.record(2, start + Duration::from_millis(1));
+        assert_eq!(est.rate, None);
+        est.record(10, start + Duration::from_secs(1));
+        assert_eq!(est.rate, Some(10.0));
+    }
+
+    #[test]
+    fn test_no_eta_without_progress() {
+        let start = Instant::now();
+        let est = Estimator::new(start);
+        assert_eq!(est.per_sec(0, Duration::ZERO), 0.0);
+        assert_eq!(est.eta(0, 100, Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Formatting of counts, durations, rates and timestamps for rendered lines.
+
+use crate::Units;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BINARY_PREFIXES: [&str; 6] = ["Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+const DECIMAL_PREFIXES: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+
+/// Scaling base, prefixes, separator and unit symbol for `units`.
+fn unit_parts(units: Units) -> (f64, &'static [&'static str], &'static str, &'static str) {
+    match units {
+        Units::Plain => (f64::INFINITY, &[], "", ""),
+        Units::BinaryBytes => (1024.0, &BINARY_PREFIXES, " ", "B"),
+        Units::DecimalBytes => (1000.0, &DECIMAL_PREFIXES, " ", "B"),
+        Units::Si => (1000.0, &DECIMAL_PREFIXES, "", ""),
+    }
+}
+
+/// Scale `value` into the largest prefix it reaches, e.g. `1.5 MiB`. Returns
+/// `None` for values too small for any prefix.
+///
+/// Values are compared after rounding to the one decimal they're shown
+/// with, so `1023.96 KiB` becomes `1.0 MiB` rather than `1024.0 KiB`.
+fn scaled(value: f64, units: Units) -> Option<String> {
+    let (base, prefixes, sep, symbol) = unit_parts(units);
+    let mut value = value;
+    let mut prefix = None;
+    for p in prefixes {
+        if (value * 10.0).round() / 10.0 < base {
+            break;
+        }
+        value /= base;
+        prefix = Some(p);
+    }
+    prefix.map(|prefix| format!("{value:.1}{sep}{prefix}{symbol}"))
+}
+
+/// Format a position or length, e.g. `1.5 MiB`, `1.5 MB` or `1.5M`.
+pub(crate) fn count(n: u64, units: Units) -> String {
+    scaled(n as f64, units).unwrap_or_else(|| {
+        let (_, _, sep, symbol) = unit_parts(units);
+        format!("{n}{sep}{symbol}")
+    })
+}
+
+/// Format a duration as `HH:MM:SS`. Hours grow past two digits as needed.
+pub(crate) fn duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Format a per-second rate in the same units as the count, e.g. `12.5/s`
+/// or `3.2 MiB/s`.
+pub(crate) fn rate(per_sec: f64, units: Units) -> String {
+    let value = scaled(per_sec, units).unwrap_or_else(|| {
+        let (_, _, sep, symbol) = unit_parts(units);
+        format!("{per_sec:.1}{sep}{symbol}")
+    });
+    format!("{value}/s")
+}
+
+/// Format a duration compactly for people, e.g. `42s`, `3m 05s`, `1h 02m` or
+/// `2d 04h`, keeping the two most significant units.
+pub(crate) fn human_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        3600..=86_399 => format!("{}h {:02}m", secs / 3600, (secs / 60) % 60),
+        _ => format!("{}d {:02}h", secs / 86_400, (secs / 3600) % 24),
+    }
+}
+
+/// Format a wall-clock time as an ISO 8601 UTC timestamp with second
+/// precision, e.g. `2024-05-01T12:00:00Z`.
+pub(crate) fn timestamp(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    )
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) date in the
+/// proleptic Gregorian calendar (Howard Hinnant's `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration(Duration::ZERO), "00:00:00");
+        assert_eq!(duration(Duration::from_millis(61_999)), "00:01:01");
+        assert_eq!(duration(Duration::from_secs(3 * 3600 + 7)), "03:00:07");
+        assert_eq!(duration(Duration::from_secs(100 * 3600)), "100:00:00");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let t = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(timestamp(t), "2024-02-29T12:34:56Z");
+        let t = UNIX_EPOCH + Duration::from_secs(4_102_444_799);
+        assert_eq!(timestamp(t), "2099-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_rate() {
+        assert_eq!(rate(0.0, Units::Plain), "0.0/s");
+        assert_eq!(rate(12.345, Units::Plain), "12.3/s");
+        assert_eq!(rate(12_345.0, Units::Plain), "12345.0/s");
+        assert_eq!(rate(512.0, Units::BinaryBytes), "512.0 B/s");
+        assert_eq!(rate(3.5 * 1024.0 * 1024.0, Units::BinaryBytes), "3.5 MiB/s");
+        assert_eq!(rate(2_500.0, Units::DecimalBytes), "2.5 kB/s");
+        assert_eq!(rate(1_200.0, Units::Si), "1.2k/s");
+        assert_eq!(rate(999.94, Units::Si), "999.9/s");
+        assert_eq!(rate(999.96, Units::Si), "1.0k/s");
+        assert_eq!(rate(1023.96, Units::BinaryBytes), "1.0 KiB/s");
+        assert_eq!(rate(999_960.0, Units::DecimalBytes), "1.0 MB/s");
+    }
+
+    #[test]
+    fn test_count_units() {
+        assert_eq!(count(4_294_967_296, Units::Plain), "4294967296");
+        assert_eq!(count(1023, Units::BinaryBytes), "1023 B");
+        assert_eq!(count(1536, Units::BinaryBytes), "1.5 KiB");
+        assert_eq!(count(1_048_524, Units::BinaryBytes), "1023.9 KiB");
+        assert_eq!(count(1_048_575, Units::BinaryBytes), "1.0 MiB");
+        assert_eq!(count(4_294_967_296, Units::BinaryBytes), "4.0 GiB");
+        assert_eq!(count(999, Units::DecimalBytes), "999 B");
+        assert_eq!(count(4_294_967_296, Units::DecimalBytes), "4.3 GB");
+        assert_eq!(count(999, Units::Si), "999");
+        assert_eq!(count(1_234, Units::Si), "1.2k");
+        assert_eq!(count(3_400_000, Units::Si), "3.4M");
+        assert_eq!(count(999_999, Units::Si), "1.0M");
+        assert_eq!(count(u64::MAX, Units::BinaryBytes), "16.0 EiB");
+    }
+
+    #[test]
+    fn test_human_duration() {
+        assert_eq!(human_duration(Duration::ZERO), "0s");
+        assert_eq!(human_duration(Duration::from_millis(42_900)), "42s");
+        assert_eq!(human_duration(Duration::from_secs(185)), "3m 05s");
+        assert_eq!(human_duration(Duration::from_secs(3720)), "1h 02m");
+        assert_eq!(
+            human_duration(Duration::from_secs(2 * 86_400 + 4 * 3600)),
+            "2d 04h"
+        );
+    }
+}
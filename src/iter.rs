@@ -0,0 +1,174 @@
+//! Progress tracking for iterators.
+
+use std::iter::FusedIterator;
+
+use crate::{ProgressBar, ProgressBarBuilder};
+
+/// Adds progress tracking to any [`Iterator`].
+///
+/// ```no_run
+/// use nanoprogress::ProgressIterator;
+///
+/// for file in ["a.txt", "b.txt", "c.txt"].iter().progress() {
+///     // each item ticks the bar by one
+/// }
+/// ```
+pub trait ProgressIterator: Iterator + Sized {
+    /// Track this iterator with a new bar on stdout. The total comes from
+    /// [`size_hint`](Iterator::size_hint) when it is exact, as for
+    /// [`ExactSizeIterator`]s; otherwise the bar is a spinner. An empty
+    /// iterator's bar finishes full.
+    fn progress(self) -> ProgressBarIter<Self> {
+        let builder = builder_for(self.size_hint());
+        track_sized(self, builder)
+    }
+
+    /// Track this iterator with `bar`, which is ticked once per item and
+    /// [finished](ProgressBar::finish) when the iterator is exhausted.
+    ///
+    /// ```no_run
+    /// use nanoprogress::{ProgressBar, ProgressIterator};
+    ///
+    /// let bar = ProgressBar::new(3).message("Resizing").start();
+    /// let sizes: Vec<usize> = ["a.png", "b.png", "c.png"]
+    ///     .iter()
+    ///     .progress_with(bar)
+    ///     .map(|name| name.len())
+    ///     .collect();
+    /// ```
+    fn progress_with(self, bar: ProgressBar) -> ProgressBarIter<Self> {
+        ProgressBarIter {
+            iter: self,
+            bar,
+            empty: false,
+        }
+    }
+}
+
+impl<I: Iterator> ProgressIterator for I {}
+
+fn builder_for(size_hint: (usize, Option<usize>)) -> ProgressBarBuilder {
+    match size_hint {
+        (lower, Some(upper)) if lower == upper => ProgressBar::new(upper as u64),
+        _ => ProgressBar::spinner(),
+    }
+}
+
+/// Track `iter` with a bar started from `builder`, which took its total from
+/// the iterator's size hint.
+fn track_sized<I: Iterator>(iter: I, builder: ProgressBarBuilder) -> ProgressBarIter<I> {
+    let empty = iter.size_hint() == (0, Some(0));
+    ProgressBarIter {
+        iter,
+        bar: builder.start(),
+        empty,
+    }
+}
+
+/// An iterator that ticks a [`ProgressBar`] for each item, created by
+/// [`ProgressIterator::progress`] and
+/// [`progress_with`](ProgressIterator::progress_with).
+pub struct ProgressBarIter<I> {
+    iter: I,
+    bar: ProgressBar,
+    /// The iterator was known to be empty, so its bar got the smallest
+    /// length, 1, and is moved to the end when finished.
+    empty: bool,
+}
+
+impl<I> ProgressBarIter<I> {
+    /// The bar this iterator ticks, e.g. to update its message.
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+}
+
+impl<I: Iterator> ProgressBarIter<I> {
+    fn track(&self, item: Option<I::Item>) -> Option<I::Item> {
+        match item {
+            Some(_) => self.bar.tick(1),
+            None => {
+                if self.empty {
+                    self.bar.set_position(u64::MAX);
+                }
+                self.bar.finish();
+            }
+        }
+        item
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressBarIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next();
+        self.track(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for ProgressBarIter<I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next_back();
+        self.track(item)
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for ProgressBarIter<I> {}
+
+impl<I: FusedIterator> FusedIterator for ProgressBarIter<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_writer;
+
+    #[test]
+    fn test_ticks_each_item_and_finishes() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(3)
+            .writer(w)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        let items: Vec<char> = "abc".chars().progress_with(bar.clone()).collect();
+        assert_eq!(items, ['a', 'b', 'c']);
+        assert_eq!(tw.output(), "0/3\n1/3\n2/3\n3/3\n");
+        assert!(bar.is_finished());
+    }
+
+    #[test]
+    fn test_empty_iterator_finishes_full() {
+        let (tw, w) = make_writer();
+        let builder = builder_for((0, Some(0)))
+            .writer(w)
+            .template("{percent}% {pos}/{len}")
+            .unwrap();
+        assert_eq!(track_sized(0..0, builder).count(), 0);
+        assert_eq!(tw.output(), "0% 0/1\n100% 1/1\n");
+    }
+
+    #[test]
+    fn test_early_exit_leaves_bar_unfinished() {
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).start();
+        let mut iter = (0..10).progress_with(bar.clone());
+        iter.next();
+        iter.next_back();
+        assert_eq!(bar.position(), 2);
+        assert!(!bar.is_finished());
+    }
+
+    #[test]
+    fn test_total_from_size_hint() {
+        let total = |hint| builder_for(hint).total;
+        assert_eq!(total((0..7).size_hint()), Some(7));
+        assert_eq!(total([1, 2].iter().size_hint()), Some(2));
+        assert_eq!(total((0..7).filter(|n| n % 2 == 0).size_hint()), None);
+        assert_eq!(total((0..).size_hint()), None);
+    }
+}
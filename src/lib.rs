@@ -21,19 +21,269 @@
 //! # Features
 //!
 //! - Zero external dependencies
-//! - Thread-safe (`Send + Sync`) — clone and share across threads
+//! - Thread-safe (`Send + Sync`) — clone and share across threads, with
+//!   lock-free ticks
 //! - Automatic TTY detection — ANSI codes are skipped when output is piped
+//! - Draw to stdout, stderr, any writer, or nowhere via [`DrawTarget`]
+//! - Color support detection honoring `NO_COLOR`, `CLICOLOR_FORCE` and
+//!   `TERM=dumb`, with a [`ColorChoice`] override
 //! - Customizable bar width, fill/empty characters, and messages
-//! - Clean finalization with colored `✔` / `✖` symbols
-//! - Automatic cleanup via `Drop`
-
+//! - Smooth bars with sub-character precision via [`BarStyle`]
+//! - Template-driven line layout (`{prefix} {bar} {pos}/{len} {percent}% {msg}`)
+//! - Elapsed time, ETA and smoothed throughput
+//! - Byte and SI [`Units`] and human-readable durations
+//! - Spinner mode for work of unknown size, with an optional steady tick that
+//!   keeps it moving while the work blocks
+//! - [`MultiProgress`] for several bars on one terminal
+//! - [`ProgressIterator`] to track any iterator with `.progress()`
+//! - [`ProgressReader`] and [`ProgressWriter`] to count bytes through any
+//!   `io::Read` or `io::Write`
+//! - Throttled, timestamped output for CI logs via [`NonTtyPolicy`]
+//! - Terminal width detection, auto-width bars and message truncation, with
+//!   layout in display columns (CJK, emoji and combining marks)
+//! - Read-only accessors and [`ProgressState`] snapshots
+//! - Clean finalization with colored `✔` / `✖` / `⚠` / `ℹ` symbols, custom
+//!   symbols, erasing, or abandoning
+//! - Finalization from a `Result`, including around a closure that may panic
+//! - Themes with 16-color, 256-color and truecolor [`Style`]s for each part
+//!   of the line
+//! - Automatic cleanup via `Drop`, with a configurable [`OnDrop`] policy
+//! - Hidden cursor while drawing, restored on finish, drop, and optionally on
+//!   panic or Ctrl-C
+
+mod counter;
+mod estimate;
+mod format;
+mod iter;
+mod multi;
+mod style;
+mod template;
+mod term;
+mod width;
+mod wrap;
+
+pub use iter::{ProgressBarIter, ProgressIterator};
+pub use multi::MultiProgress;
+pub use style::{Color, ColorChoice, Style, Theme};
+pub use template::TemplateError;
+pub use wrap::{ProgressReader, ProgressWriter};
+
+use counter::Counter;
+use estimate::{Clock, Estimator};
+use multi::{MultiState, Slot};
+use std::fmt::Display;
 use std::io::{self, Write};
-use std::sync::{Arc, Mutex};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError, Weak};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant, SystemTime};
+use style::ColorLevel;
+use template::{Field, Piece, Template};
+use term::Stream;
+use width::{char_width, str_width};
+
+/// Narrowest an auto-width bar gets before the message is truncated instead.
+const MIN_AUTO_WIDTH: usize = 10;
+
+const DEFAULT_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// How often a bar prints a line when its output is not a terminal.
+///
+/// Without a terminal every redraw becomes a new line, which can make CI logs
+/// huge. Whatever the policy, the initial state is printed at start and the
+/// latest state is printed before the final symbol and message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonTtyPolicy {
+    /// Print a line on every redraw.
+    #[default]
+    EveryRedraw,
+    /// Print a line each time progress crosses a multiple of this many
+    /// percent, e.g. `Percent(10)` prints at 10%, 20%, …. Spinners have no
+    /// percentage and print only at start and finish.
+    Percent(u8),
+    /// Print a line at most once per interval.
+    Interval(Duration),
+    /// Print only at start and finish.
+    StartAndFinish,
+}
+
+/// How a bar ended, or that it hasn't yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// Not finalized yet.
+    InProgress,
+    /// Finished with [`finish`](ProgressBar::finish), or by a
+    /// [`ProgressIterator`] running out.
+    Finished,
+    /// Finalized with [`success`](ProgressBar::success).
+    Succeeded,
+    /// Finalized with [`fail`](ProgressBar::fail).
+    Failed,
+    /// Finalized with [`warn`](ProgressBar::warn).
+    Warned,
+    /// Finalized with [`info`](ProgressBar::info).
+    Info,
+    /// Finalized with [`skip`](ProgressBar::skip).
+    Skipped,
+    /// Finalized with [`finish_with`](ProgressBar::finish_with).
+    Custom,
+    /// Erased with [`finish_and_clear`](ProgressBar::finish_and_clear).
+    Cleared,
+    /// Stopped with [`abandon`](ProgressBar::abandon), or dropped unfinished
+    /// with [`OnDrop::Abandon`] or [`OnDrop::Leave`].
+    Abandoned,
+}
+
+/// What happens when the last clone of an unfinished bar is dropped, e.g.
+/// when a `?` returns early before the bar is finalized.
+///
+/// ```no_run
+/// use nanoprogress::{OnDrop, ProgressBar};
+///
+/// fn download() -> Result<(), std::io::Error> {
+///     let bar = ProgressBar::new(100)
+///         .on_drop(OnDrop::Fail("Download interrupted".into()))
+///         .start();
+///     // An error returned by `?` here leaves `✖ Download interrupted`.
+///     bar.success("Downloaded");
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OnDrop {
+    /// Stop drawing and leave the screen exactly as it is. The cursor stays
+    /// at the end of the bar's line, so whatever is printed next continues
+    /// that line. A bar in a [`MultiProgress`] is abandoned instead, since it
+    /// can't stay in the live area.
+    Leave,
+    /// Erase the bar, like [`finish_and_clear`](ProgressBar::finish_and_clear).
+    Clear,
+    /// Finalize with [`fail`](ProgressBar::fail) and this message.
+    Fail(String),
+    /// Finalize with [`success`](ProgressBar::success) and this message.
+    Success(String),
+    /// Leave the bar drawn and move output past it, like
+    /// [`abandon`](ProgressBar::abandon).
+    #[default]
+    Abandon,
+}
+
+/// A point-in-time copy of a bar's progress, from
+/// [`ProgressBar::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ProgressState {
+    /// Current position.
+    pub position: u64,
+    /// Length, or `None` for a spinner.
+    pub length: Option<u64>,
+    /// Time since the bar was started.
+    pub elapsed: Duration,
+    /// Estimated time remaining, if there is a rate and a length.
+    pub eta: Option<Duration>,
+    /// Smoothed throughput in items per second.
+    pub per_sec: f64,
+    /// Whether and how the bar was finalized.
+    pub outcome: Outcome,
+}
+
+/// How `{pos}`, `{len}` and `{per_sec}` are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    /// Raw integers, e.g. `4294967296` and `12.5/s`.
+    #[default]
+    Plain,
+    /// Bytes with binary prefixes, e.g. `4.0 GiB` and `3.5 MiB/s`.
+    BinaryBytes,
+    /// Bytes with decimal prefixes, e.g. `4.3 GB` and `3.7 MB/s`.
+    DecimalBytes,
+    /// Counts with SI prefixes, e.g. `1.2k` and `3.4M/s`.
+    Si,
+}
+
+/// Where a bar (or a [`MultiProgress`]) draws.
+///
+/// TTY mode and the terminal width are detected on the chosen stream, so a
+/// bar on stderr redraws in place even while stdout is piped.
+#[derive(Default)]
+pub enum DrawTarget {
+    /// Standard output.
+    #[default]
+    Stdout,
+    /// Standard error, keeping the bar out of data written to stdout.
+    Stderr,
+    /// A custom writer. Defaults to non-TTY mode unless overridden with
+    /// [`.tty(true)`](ProgressBarBuilder::tty).
+    Writer(Box<dyn Write + Send>),
+    /// Draw nothing. The bar still counts, so its position, rate and ETA
+    /// stay available, but lines are never formatted.
+    Hidden,
+}
+
+impl DrawTarget {
+    /// The writer to draw to, and the stream to query for TTY mode and width
+    /// if it is one.
+    fn into_parts(self) -> (Box<dyn Write + Send>, Option<Stream>) {
+        match self {
+            DrawTarget::Stdout => (Box::new(io::stdout()), Some(Stream::Stdout)),
+            DrawTarget::Stderr => (Box::new(io::stderr()), Some(Stream::Stderr)),
+            DrawTarget::Writer(writer) => (writer, None),
+            DrawTarget::Hidden => (Box::new(io::sink()), None),
+        }
+    }
+}
+
+/// How the leading edge of the bar is drawn.
+///
+/// With whole cells a 40-column bar only moves every 2.5%, so large jobs look
+/// frozen between steps. The smooth styles draw the cell at the edge partly
+/// filled, while the [`fill`](ProgressBarBuilder::fill) and
+/// [`empty`](ProgressBarBuilder::empty) characters still draw whole cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BarStyle {
+    /// Whole cells only.
+    #[default]
+    Cells,
+    /// Eight steps per cell using the block elements `▏▎▍▌▋▊▉`.
+    Smooth,
+    /// Ten steps per cell using the digits `1`–`9`, for terminals and logs
+    /// without block elements.
+    SmoothAscii,
+}
+
+impl BarStyle {
+    /// Glyphs for a partly filled cell, from least to most filled.
+    fn partials(self) -> &'static [char] {
+        match self {
+            BarStyle::Cells => &[],
+            BarStyle::Smooth => &['▏', '▎', '▍', '▌', '▋', '▊', '▉'],
+            BarStyle::SmoothAscii => &['1', '2', '3', '4', '5', '6', '7', '8', '9'],
+        }
+    }
+}
 
 struct BarConfig {
     width: usize,
     fill: char,
     empty: char,
+    style: BarStyle,
+    theme: Theme,
+    template: Template,
+    /// Whether the template was set with
+    /// [`template`](ProgressBarBuilder::template), rather than a default.
+    custom_template: bool,
+    frames: Vec<String>,
+    clear_on_finish: bool,
+    redraw_interval: Duration,
+    non_tty_policy: NonTtyPolicy,
+    log_timestamps: bool,
+    units: Units,
+    human_durations: bool,
+    auto_width: bool,
+    max_width: Option<usize>,
+    on_drop: OnDrop,
 }
 
 impl Default for BarConfig {
@@ -42,92 +292,542 @@ impl Default for BarConfig {
             width: 40,
             fill: '█',
             empty: '░',
+            style: BarStyle::default(),
+            theme: Theme::default(),
+            template: Template::default(),
+            custom_template: false,
+            frames: DEFAULT_FRAMES.iter().map(|f| f.to_string()).collect(),
+            clear_on_finish: false,
+            redraw_interval: Duration::ZERO,
+            non_tty_policy: NonTtyPolicy::default(),
+            log_timestamps: false,
+            units: Units::default(),
+            human_durations: false,
+            auto_width: false,
+            max_width: None,
+            on_drop: OnDrop::default(),
         }
     }
 }
 
+/// Where a bar's lines go: straight to its own writer, into a line of a
+/// [`MultiProgress`], or nowhere.
+enum Output {
+    Writer(Box<dyn Write + Send>),
+    Multi(Slot),
+    /// Nothing is drawn, and lines are never even formatted.
+    Hidden,
+}
+
 struct ProgressBarState {
+    /// Position and length as of the last [`sync`](ProgressBarState::sync).
+    /// Lines are drawn from these, not from the counter.
     current: u64,
-    total: u64,
+    /// `None` in spinner mode, where the amount of work isn't known.
+    total: Option<u64>,
+    counter: Arc<Counter>,
+    prefix: String,
     message: String,
-    finished: bool,
-    writer: Box<dyn Write + Send>,
+    outcome: Outcome,
+    output: Output,
+    /// The stream to query for the terminal's column count, if the output is
+    /// one.
+    stream: Option<Stream>,
     config: BarConfig,
     is_tty: bool,
+    color: ColorLevel,
+    clock: Clock,
+    started: Instant,
+    estimator: Estimator,
+    frame: usize,
+    last_draw: Option<Instant>,
+    /// Percentage at the last draw, for [`NonTtyPolicy::Percent`].
+    last_percent: u64,
+    /// Progress changed since the last draw, because a redraw was skipped.
+    dirty: bool,
+    /// The steady-tick thread, if one is running. Unparked to stop it.
+    ticker: Option<Thread>,
+    /// Whether the bar has hidden the cursor, and the stream's
+    /// [`cursor_epoch`](term::cursor_epoch) when it did.
+    cursor_hidden: bool,
+    cursor_epoch: usize,
 }
 
 impl ProgressBarState {
-    fn render(&mut self) {
-        let ratio = self.current as f64 / self.total.max(1) as f64;
-        let filled = (ratio * self.config.width as f64).round() as usize;
-        let empty = self.config.width - filled;
-        let percent = (ratio * 100.0) as u64;
+    fn is_finished(&self) -> bool {
+        self.outcome != Outcome::InProgress
+    }
 
-        let bar: String = std::iter::repeat_n(self.config.fill, filled)
-            .chain(std::iter::repeat_n(self.config.empty, empty))
-            .collect();
+    fn ratio(&self) -> f64 {
+        match self.total {
+            Some(total) => self.current as f64 / total.max(1) as f64,
+            None => 0.0,
+        }
+    }
+
+    fn percent(&self) -> u64 {
+        (self.ratio() * 100.0) as u64
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.started)
+    }
+
+    fn per_sec(&self) -> f64 {
+        self.estimator.per_sec(self.current, self.elapsed())
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        self.estimator.eta(self.current, total, self.elapsed())
+    }
+
+    fn field(&self, field: Field) -> String {
+        match field {
+            Field::Prefix => self.prefix.clone(),
+            // Spinners have nothing to measure against, so length-relative
+            // fields render empty.
+            Field::Bar | Field::Len | Field::Percent | Field::Eta if self.total.is_none() => {
+                String::new()
+            }
+            Field::Bar => self.bar(self.config.width),
+            Field::Pos => format::count(self.current, self.config.units),
+            Field::Len => format::count(self.total.unwrap_or_default(), self.config.units),
+            Field::Percent => self.paint(self.config.theme.percent, &self.percent().to_string()),
+            Field::Msg => self.paint(self.config.theme.message, &self.message),
+            Field::Elapsed => self.duration(self.elapsed()),
+            Field::Eta => match self.eta() {
+                Some(eta) => self.duration(eta),
+                None if self.config.human_durations => "?".to_string(),
+                None => "--:--:--".to_string(),
+            },
+            Field::PerSec => format::rate(self.per_sec(), self.config.units),
+            Field::Spinner => match self.config.frames.len() {
+                0 => String::new(),
+                n => self.config.frames[self.frame % n].clone(),
+            },
+        }
+    }
 
-        let line = if self.message.is_empty() {
-            format!("[{}] {:>3}% {}/{}", bar, percent, self.current, self.total)
+    fn duration(&self, d: Duration) -> String {
+        if self.config.human_durations {
+            format::human_duration(d)
         } else {
-            format!(
-                "[{}] {:>3}% {}/{} {}",
-                bar, percent, self.current, self.total, self.message
-            )
+            format::duration(d)
+        }
+    }
+
+    /// Draw the bar track, exactly `width` columns wide even when the fill or
+    /// empty characters are double-width.
+    fn bar(&self, width: usize) -> String {
+        let fill_width = char_width(self.config.fill).max(1);
+        let empty_width = char_width(self.config.empty).max(1);
+        let partials = self.config.style.partials();
+        let steps = partials.len() + 1;
+        let units = ((self.ratio() * (width * steps) as f64).round() as usize).min(width * steps);
+        let filled = units / steps / fill_width;
+        let remainder = units - filled * fill_width * steps;
+        let partial = remainder
+            .min(steps - 1)
+            .checked_sub(1)
+            .filter(|_| filled * fill_width < width)
+            .map(|step| partials[step]);
+        let rest = width - filled * fill_width - usize::from(partial.is_some());
+        // A wide fill character that doesn't fit in the last columns leaves
+        // them blank once the bar reaches them, so a full bar looks full.
+        let reached = units / steps > filled * fill_width && rest < fill_width;
+        let empty = if reached { 0 } else { rest / empty_width };
+        let done: String = std::iter::repeat_n(self.config.fill, filled)
+            .chain(partial)
+            .collect();
+        let todo: String = std::iter::repeat_n(self.config.empty, empty)
+            .chain(std::iter::repeat_n(' ', rest - empty * empty_width))
+            .collect();
+        self.paint(self.config.theme.filled, &done) + &self.paint(self.config.theme.empty, &todo)
+    }
+
+    fn paint(&self, style: Style, text: &str) -> String {
+        style.paint(text, self.color)
+    }
+
+    /// The widest a line may be: the explicit maximum if one was set,
+    /// otherwise the terminal's width in TTY mode.
+    fn max_width(&self) -> Option<usize> {
+        if self.config.max_width.is_some() {
+            return self.config.max_width;
+        }
+        if !self.is_tty {
+            return None;
+        }
+        match &self.output {
+            Output::Writer(_) => self.stream.and_then(term::width),
+            Output::Multi(slot) => slot.term_width(),
+            Output::Hidden => None,
+        }
+    }
+
+    /// Render the template into a single line that fits [`max_width`].
+    ///
+    /// Fixed fields are laid out first. An auto-width bar takes the space the
+    /// message doesn't need, and the message is cut short with `…` if there is
+    /// still not enough room. As a last resort the whole line is truncated.
+    ///
+    /// [`max_width`]: ProgressBarState::max_width
+    fn line(&self) -> String {
+        let Some(max) = self.max_width() else {
+            return self.assemble(self.config.width, usize::MAX);
+        };
+        let pieces = &self.config.template.pieces;
+        let auto_bar = self.config.auto_width && self.total.is_some();
+        let (mut fixed, mut bars, mut msgs) = (0, 0, 0);
+        for piece in pieces {
+            match piece {
+                Piece::Literal(text) => fixed += str_width(text),
+                Piece::Field(Field::Msg, _) => msgs += 1,
+                Piece::Field(Field::Bar, _) if auto_bar => bars += 1,
+                Piece::Field(field, spec) => fixed += str_width(&spec.apply(self.field(*field))),
+            }
+        }
+        let room = max.saturating_sub(fixed);
+        let msg_width = pieces
+            .iter()
+            .find_map(|piece| match piece {
+                Piece::Field(Field::Msg, spec) => Some(spec.apply(self.message.clone())),
+                _ => None,
+            })
+            .map_or(0, |msg| str_width(&msg));
+        let bar_width = match room.saturating_sub(msgs * msg_width).checked_div(bars) {
+            Some(wanted) => wanted.max(MIN_AUTO_WIDTH.min(room / bars)),
+            None => self.config.width,
+        };
+        let msg_room = match msgs {
+            0 => 0,
+            n => room.saturating_sub(bars * bar_width) / n,
         };
+        width::truncate(&self.assemble(bar_width, msg_room), max)
+    }
 
-        if self.is_tty {
-            write!(self.writer, "\r{}", line).ok();
-        } else {
-            writeln!(self.writer, "{}", line).ok();
+    /// Join the template's pieces. Fields that render empty at the end of the
+    /// line (usually `{msg}`) take the whitespace before them along, so they
+    /// don't leave dangling spaces.
+    fn assemble(&self, bar_width: usize, msg_room: usize) -> String {
+        let mut line = String::new();
+        let mut content_end = 0;
+        for piece in &self.config.template.pieces {
+            match piece {
+                Piece::Literal(text) => line.push_str(text),
+                Piece::Field(field, spec) => {
+                    let value = match field {
+                        Field::Bar if self.total.is_some() => self.bar(bar_width),
+                        Field::Msg => {
+                            width::truncate(&spec.apply(self.field(Field::Msg)), msg_room)
+                        }
+                        _ => spec.apply(self.field(*field)),
+                    };
+                    if value.is_empty() {
+                        content_end = line.trim_end().len().max(content_end);
+                    } else {
+                        line.push_str(&value);
+                        content_end = line.len();
+                    }
+                }
+            }
+        }
+        if content_end < line.len() && line[content_end..].trim().is_empty() {
+            line.truncate(content_end);
+        }
+        line
+    }
+
+    /// Take in the latest position and length from the counter, which ticks
+    /// update without the lock.
+    fn sync(&mut self) {
+        let (current, total) = (self.counter.position(), self.counter.length());
+        if (current, total) != (self.current, self.total) {
+            self.current = current;
+            self.total = total;
+            self.estimator.record(current, self.clock.now());
+            self.dirty = true;
+        }
+    }
+
+    /// Set the length and redraw if due. A spinner still on the default
+    /// spinner template takes the default bar template, which shows it.
+    fn set_length(&mut self, length: u64) {
+        if self.total.is_none() && !self.config.custom_template {
+            self.config.template = Template::default();
         }
-        self.writer.flush().ok();
+        self.counter.set_length(length);
+        self.update();
     }
 
-    fn finalize(&mut self, symbol: &str, color_code: &str, msg: &str) {
-        if self.finished {
+    /// Sync after a change made under the lock and redraw if due.
+    fn update(&mut self) {
+        if self.is_finished() {
             return;
         }
-        self.finished = true;
+        self.sync();
+        let now = self.clock.now();
+        self.redraw(now);
+    }
 
-        if self.is_tty {
-            write!(
-                self.writer,
-                "\r\x1b[2K{}{}\x1b[0m {}\n",
-                color_code, symbol, msg
-            )
-            .ok();
+    /// Render if the redraw interval has passed since the last draw and, when
+    /// not on a terminal, the [`NonTtyPolicy`] allows a new line; otherwise
+    /// just remember that the display is out of date.
+    fn redraw(&mut self, now: Instant) {
+        let due = match self.last_draw {
+            Some(last) => {
+                let since = now.saturating_duration_since(last);
+                since >= self.config.redraw_interval && (self.is_tty || self.log_due(since))
+            }
+            None => true,
+        };
+        if due {
+            self.render();
         } else {
-            writeln!(self.writer, "{} {}", symbol, msg).ok();
+            self.dirty = true;
         }
-        self.writer.flush().ok();
     }
-}
 
-// --- TTY Detection ---
+    fn log_due(&self, since_last_draw: Duration) -> bool {
+        match self.config.non_tty_policy {
+            NonTtyPolicy::EveryRedraw | NonTtyPolicy::Percent(0) => true,
+            NonTtyPolicy::Percent(step) => {
+                let step = u64::from(step);
+                self.total.is_some() && self.percent() / step > self.last_percent / step
+            }
+            NonTtyPolicy::Interval(every) => since_last_draw >= every,
+            NonTtyPolicy::StartAndFinish => false,
+        }
+    }
 
-#[cfg(unix)]
-fn is_stdout_tty() -> bool {
-    extern "C" {
-        fn isatty(fd: std::os::raw::c_int) -> std::os::raw::c_int;
+    /// Prefix non-TTY lines with the wall-clock time and elapsed time, if
+    /// enabled.
+    fn stamp(&self, line: String) -> String {
+        if self.is_tty || !self.config.log_timestamps {
+            return line;
+        }
+        format!(
+            "{} [{}] {}",
+            format::timestamp(SystemTime::now()),
+            format::duration(self.elapsed()),
+            line
+        )
     }
-    unsafe { isatty(1) != 0 }
-}
 
-#[cfg(windows)]
-fn is_stdout_tty() -> bool {
-    use std::os::windows::io::AsRawHandle;
-    extern "system" {
-        fn GetConsoleMode(handle: *mut std::ffi::c_void, mode: *mut u32) -> i32;
+    fn render(&mut self) {
+        if matches!(self.output, Output::Hidden) {
+            return;
+        }
+        let line = self.stamp(self.line());
+        let now = self.clock.now();
+        self.frame = self.frame.wrapping_add(1);
+        self.last_draw = Some(now);
+        // An interval too long to add, such as `Duration::MAX`, means no
+        // redraws until finalization.
+        self.counter
+            .next_draw_at(now.checked_add(self.config.redraw_interval));
+        self.last_percent = self.percent();
+        self.dirty = false;
+
+        self.hide_cursor();
+        match &mut self.output {
+            Output::Writer(writer) => {
+                if self.is_tty {
+                    write!(writer, "\r{}", line).ok();
+                } else {
+                    writeln!(writer, "{}", line).ok();
+                }
+                writer.flush().ok();
+            }
+            Output::Multi(slot) => slot.draw(&self.prefix, &line),
+            Output::Hidden => {}
+        }
+    }
+
+    fn finalize(&mut self, outcome: Outcome, symbol: &str, style: Style, msg: &str) {
+        if self.is_finished() {
+            return;
+        }
+        if self.dirty {
+            self.render();
+        }
+        let line = (!self.config.clear_on_finish).then(|| {
+            let line = format!("{} {}", self.paint(style, symbol), msg);
+            let line = match self.max_width() {
+                Some(max) => width::truncate(&line, max),
+                None => line,
+            };
+            self.stamp(line)
+        });
+        self.close(outcome, line);
+    }
+
+    /// Finalize, leaving the latest state of the bar as its last line. When
+    /// not on a terminal that line has already been printed.
+    fn finish(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        if self.dirty {
+            self.render();
+        }
+        let line = (self.is_tty && !self.config.clear_on_finish).then(|| self.line());
+        self.close(Outcome::Finished, line);
+    }
+
+    /// Record the outcome and replace the bar's line with `line`, or erase it.
+    fn close(&mut self, outcome: Outcome, line: Option<String>) {
+        self.outcome = outcome;
+        self.counter.finish();
+        if let Some(ticker) = self.ticker.take() {
+            ticker.unpark();
+        }
+        self.show_cursor();
+        match &mut self.output {
+            Output::Writer(writer) => {
+                if self.is_tty {
+                    write!(writer, "\r\x1b[2K").ok();
+                }
+                if let Some(line) = line {
+                    writeln!(writer, "{}", line).ok();
+                }
+                writer.flush().ok();
+            }
+            Output::Multi(slot) => slot.finish(line.as_deref()),
+            Output::Hidden => {}
+        }
+    }
+
+    fn println(&mut self, text: &str) {
+        let finished = self.is_finished();
+        match &mut self.output {
+            Output::Writer(writer) => {
+                if self.is_tty && !finished {
+                    writeln!(writer, "\r\x1b[2K{}", text).ok();
+                    self.render();
+                } else {
+                    writeln!(writer, "{}", text).ok();
+                    writer.flush().ok();
+                }
+            }
+            Output::Multi(slot) => slot.println(text),
+            Output::Hidden => {}
+        }
+    }
+
+    fn suspend<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let finished = self.is_finished();
+        match &mut self.output {
+            Output::Writer(writer) => {
+                if !self.is_tty || finished {
+                    return f();
+                }
+                write!(writer, "\r\x1b[2K").ok();
+                self.show_cursor();
+                let result = f();
+                self.sync();
+                self.render();
+                result
+            }
+            Output::Multi(slot) => slot.suspend(f),
+            Output::Hidden => f(),
+        }
+    }
+
+    /// Hide the terminal cursor so it doesn't blink on top of the bar, unless
+    /// it's hidden already. Only bars drawing to their own terminal do this;
+    /// a [`MultiProgress`] manages the cursor for its children.
+    ///
+    /// The panic hook shows the cursor, so it's hidden again if that happened
+    /// since, in case the panic was caught.
+    fn hide_cursor(&mut self) {
+        let (Output::Writer(writer), true) = (&mut self.output, self.is_tty) else {
+            return;
+        };
+        let epoch = self.stream.map_or(0, term::cursor_epoch);
+        if self.cursor_hidden && self.cursor_epoch == epoch {
+            return;
+        }
+        writer.write_all(term::HIDE_CURSOR.as_bytes()).ok();
+        if let (Some(stream), false) = (self.stream, self.cursor_hidden) {
+            term::track_cursor(stream, true);
+        }
+        self.cursor_hidden = true;
+        self.cursor_epoch = epoch;
     }
-    let handle = io::stdout().as_raw_handle();
-    let mut mode: u32 = 0;
-    unsafe { GetConsoleMode(handle as *mut _, &mut mode) != 0 }
-}
 
-#[cfg(not(any(unix, windows)))]
-fn is_stdout_tty() -> bool {
-    false
+    fn show_cursor(&mut self) {
+        if !self.cursor_hidden {
+            return;
+        }
+        if let Output::Writer(writer) = &mut self.output {
+            writer.write_all(term::SHOW_CURSOR.as_bytes()).ok();
+            writer.flush().ok();
+            if let Some(stream) = self.stream {
+                term::track_cursor(stream, false);
+            }
+        }
+        self.cursor_hidden = false;
+    }
+
+    /// Erase the bar. When not on a terminal nothing can be erased, so the
+    /// lines already printed stay.
+    fn finish_and_clear(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        if self.dirty {
+            self.render();
+        }
+        self.close(Outcome::Cleared, None);
+    }
+
+    /// Stop drawing, leaving the screen as it is. See [`OnDrop::Leave`].
+    fn leave(&mut self) {
+        if matches!(self.output, Output::Multi(_)) {
+            return self.abandon();
+        }
+        if self.is_finished() {
+            return;
+        }
+        if self.dirty {
+            self.render();
+        }
+        self.outcome = Outcome::Abandoned;
+        self.show_cursor();
+    }
+
+    /// End the bar according to its [`OnDrop`] policy, once the last handle
+    /// to it is gone.
+    fn drop_unfinished(&mut self) {
+        match self.config.on_drop.clone() {
+            OnDrop::Leave => self.leave(),
+            OnDrop::Clear => self.finish_and_clear(),
+            OnDrop::Fail(msg) => {
+                let style = self.config.theme.fail;
+                self.finalize(Outcome::Failed, "✖", style, &msg);
+            }
+            OnDrop::Success(msg) => {
+                let style = self.config.theme.success;
+                self.finalize(Outcome::Succeeded, "✔", style, &msg);
+            }
+            OnDrop::Abandon => self.abandon(),
+        }
+    }
+
+    /// Leave the bar showing its latest state, unfinished, moving output past
+    /// it.
+    fn abandon(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        if self.dirty {
+            self.render();
+        }
+        let line = self.is_tty.then(|| self.line());
+        self.close(Outcome::Abandoned, line);
+    }
 }
 
 // --- Builder ---
@@ -148,11 +848,15 @@ fn is_stdout_tty() -> bool {
 ///     .start();
 /// ```
 pub struct ProgressBarBuilder {
-    total: u64,
+    total: Option<u64>,
     config: BarConfig,
+    prefix: String,
     message: String,
-    writer: Option<Box<dyn Write + Send>>,
+    target: DrawTarget,
     tty_override: Option<bool>,
+    color: ColorChoice,
+    restore_cursor: bool,
+    clock: Clock,
 }
 
 impl ProgressBarBuilder {
@@ -162,6 +866,24 @@ impl ProgressBarBuilder {
         self
     }
 
+    /// Stretch the bar to fill the terminal width left over by the rest of the
+    /// line, instead of using a fixed [`width`](ProgressBarBuilder::width).
+    /// When the terminal width is unknown the fixed width is used.
+    pub fn auto_width(mut self, enabled: bool) -> Self {
+        self.config.auto_width = enabled;
+        self
+    }
+
+    /// Never render lines wider than `columns`, overriding terminal width
+    /// detection. Applies in non-TTY mode too.
+    ///
+    /// Lines are always kept within the terminal width when it is known: an
+    /// overlong message is cut short with `…` so it doesn't wrap.
+    pub fn max_width(mut self, columns: usize) -> Self {
+        self.config.max_width = Some(columns);
+        self
+    }
+
     /// Set the fill character for completed progress. Default: `█`.
     pub fn fill(mut self, ch: char) -> Self {
         self.config.fill = ch;
@@ -174,16 +896,205 @@ impl ProgressBarBuilder {
         self
     }
 
+    /// Set the colors and attributes of the bar, percentage, message and
+    /// final symbols. Default: [`Theme::default`], which only colors the
+    /// symbols. Styles apply in TTY mode only.
+    ///
+    /// ```no_run
+    /// use nanoprogress::{ProgressBar, Theme};
+    ///
+    /// let bar = ProgressBar::new(100).theme(Theme::vivid()).start();
+    /// ```
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.config.theme = theme;
+        self
+    }
+
+    /// Set how the leading edge of the bar is drawn. Default:
+    /// [`BarStyle::Cells`].
+    ///
+    /// ```no_run
+    /// use nanoprogress::{BarStyle, ProgressBar};
+    ///
+    /// let bar = ProgressBar::new(10_000).bar_style(BarStyle::Smooth).start();
+    /// ```
+    pub fn bar_style(mut self, style: BarStyle) -> Self {
+        self.config.style = style;
+        self
+    }
+
     /// Set an initial message displayed after the count.
     pub fn message(mut self, msg: &str) -> Self {
         self.message = msg.to_string();
         self
     }
 
+    /// Set a prefix, shown wherever the template places `{prefix}`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Set the line layout. Default: `"[{bar}] {percent:>3}% {pos}/{len} {msg}"`.
+    ///
+    /// Spinners default to `"{spinner} {pos} {msg}"`.
+    ///
+    /// Available placeholders are `{prefix}`, `{bar}`, `{pos}`, `{len}`,
+    /// `{percent}`, `{msg}`, `{elapsed}`, `{eta}`, `{per_sec}` and `{spinner}`.
+    /// Each may take a format spec of an optional alignment (`<`, `>`, `^`) and
+    /// a minimum width, e.g. `{pos:>5}`. Use `{{` and `}}` for literal braces.
+    ///
+    /// The template is parsed here, once, and reused for every render.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    ///
+    /// let bar = ProgressBar::new(100)
+    ///     .prefix("fetch")
+    ///     .template("{prefix} {bar} {pos}/{len} {percent}% {msg}")?
+    ///     .start();
+    /// # Ok::<(), nanoprogress::TemplateError>(())
+    /// ```
+    pub fn template(mut self, template: &str) -> Result<Self, TemplateError> {
+        self.config.template = Template::parse(template)?;
+        self.config.custom_template = true;
+        Ok(self)
+    }
+
+    /// Set the frames cycled through by `{spinner}`, one per render.
+    /// Default: a braille dot spinner (`⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏`).
+    pub fn frames(mut self, frames: &[&str]) -> Self {
+        self.config.frames = frames.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Erase the bar when it is finalized, instead of printing the final
+    /// symbol and message. Default: `false`.
+    pub fn clear_on_finish(mut self, clear: bool) -> Self {
+        self.config.clear_on_finish = clear;
+        self
+    }
+
+    /// What to do if the last clone of the bar is dropped before it's
+    /// finalized. Default: [`OnDrop::Abandon`].
+    pub fn on_drop(mut self, on_drop: OnDrop) -> Self {
+        self.config.on_drop = on_drop;
+        self
+    }
+
+    /// Show the cursor again if the program panics or is interrupted with
+    /// Ctrl-C while the bar is hiding it. On Ctrl-C this also moves past the
+    /// bar's line so the shell prompt starts on a fresh one. A panic may be
+    /// caught, so it leaves the line alone, and the bar hides the cursor
+    /// again on its next redraw. Default: `false`.
+    ///
+    /// This installs a panic hook and, on unix, a SIGINT handler, once per
+    /// process. Both call whatever hook or handler was installed before them,
+    /// so a program's own Ctrl-C handling keeps working; without one, the
+    /// program still exits from the signal.
+    pub fn restore_cursor_on_exit(mut self, restore: bool) -> Self {
+        self.restore_cursor = restore;
+        self
+    }
+
+    /// Set the minimum time between redraws. Ticks that arrive sooner only
+    /// update the count; the latest state is drawn by the next redraw that is
+    /// due, and always on finalization. [`Duration::MAX`] draws only the
+    /// initial and final state. Default: no limit.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// let bar = ProgressBar::new(1_000_000)
+    ///     .redraw_interval(Duration::from_millis(100))
+    ///     .start();
+    /// for _ in 0..1_000_000 {
+    ///     bar.tick(1); // draws at most ten times a second
+    /// }
+    /// bar.success("Done");
+    /// ```
+    pub fn redraw_interval(mut self, interval: Duration) -> Self {
+        self.config.redraw_interval = interval;
+        self
+    }
+
+    /// Limit redraws to `per_sec` per second, like
+    /// [`redraw_interval`](ProgressBarBuilder::redraw_interval) with an
+    /// interval of `1s / per_sec`. A value of 0 removes the limit.
+    pub fn max_redraws_per_sec(mut self, per_sec: u32) -> Self {
+        self.config.redraw_interval = match per_sec {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        };
+        self
+    }
+
+    /// Set how positions, lengths and rates are written. Default:
+    /// [`Units::Plain`].
+    ///
+    /// ```no_run
+    /// use nanoprogress::{ProgressBar, Units};
+    ///
+    /// // "[…]  25% 1.0 GiB/4.0 GiB 112.4 MiB/s"
+    /// let bar = ProgressBar::new(4 << 30)
+    ///     .units(Units::BinaryBytes)
+    ///     .template("[{bar}] {percent:>3}% {pos}/{len} {per_sec}")?
+    ///     .start();
+    /// # Ok::<(), nanoprogress::TemplateError>(())
+    /// ```
+    pub fn units(mut self, units: Units) -> Self {
+        self.config.units = units;
+        self
+    }
+
+    /// Write `{elapsed}` and `{eta}` compactly for people, e.g. `3m 05s` or
+    /// `1h 02m`, instead of `HH:MM:SS`. Default: `false`.
+    pub fn human_durations(mut self, enabled: bool) -> Self {
+        self.config.human_durations = enabled;
+        self
+    }
+
+    /// Set how often lines are printed when the output is not a terminal.
+    /// Default: [`NonTtyPolicy::EveryRedraw`]. Has no effect in TTY mode.
+    ///
+    /// ```no_run
+    /// use nanoprogress::{NonTtyPolicy, ProgressBar};
+    ///
+    /// let bar = ProgressBar::new(10_000)
+    ///     .non_tty_policy(NonTtyPolicy::Percent(10))
+    ///     .log_timestamps(true)
+    ///     .start();
+    /// ```
+    pub fn non_tty_policy(mut self, policy: NonTtyPolicy) -> Self {
+        self.config.non_tty_policy = policy;
+        self
+    }
+
+    /// Prefix each non-TTY line with a UTC timestamp and the elapsed time,
+    /// e.g. `2024-05-01T12:00:00Z [00:01:05] …`. Default: `false`. Has no
+    /// effect in TTY mode.
+    pub fn log_timestamps(mut self, enabled: bool) -> Self {
+        self.config.log_timestamps = enabled;
+        self
+    }
+
     /// Direct output to a custom writer instead of stdout.
     /// Custom writers default to non-TTY mode unless overridden with [`.tty(true)`](ProgressBarBuilder::tty).
-    pub fn writer(mut self, writer: Box<dyn Write + Send>) -> Self {
-        self.writer = Some(writer);
+    pub fn writer(self, writer: Box<dyn Write + Send>) -> Self {
+        self.draw_target(DrawTarget::Writer(writer))
+    }
+
+    /// Choose where the bar draws. Default: [`DrawTarget::Stdout`].
+    ///
+    /// ```no_run
+    /// use nanoprogress::{DrawTarget, ProgressBar};
+    ///
+    /// // Keep stdout clean for piped data.
+    /// let bar = ProgressBar::new(100).draw_target(DrawTarget::Stderr).start();
+    /// ```
+    pub fn draw_target(mut self, target: DrawTarget) -> Self {
+        self.target = target;
         self
     }
 
@@ -193,32 +1104,90 @@ impl ProgressBarBuilder {
         self
     }
 
+    /// Choose whether to use colors, overriding auto-detection. Default:
+    /// [`ColorChoice::Auto`], which uses colors in TTY mode unless `NO_COLOR`
+    /// is set, `CLICOLOR` is `0` or `TERM` is `dumb`, and also when piped if
+    /// `CLICOLOR_FORCE` is set.
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        self.color = choice;
+        self
+    }
+
+    /// Replace the system clock, so timing-dependent output is deterministic.
+    #[cfg(test)]
+    fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Build and start the progress bar, rendering the initial state immediately.
-    pub fn start(self) -> ProgressBar {
-        let total = if self.total == 0 { 1 } else { self.total };
-        let has_custom_writer = self.writer.is_some();
-        let writer = self.writer.unwrap_or_else(|| Box::new(io::stdout()));
-        let is_tty = self.tty_override.unwrap_or_else(|| {
-            if has_custom_writer {
-                false
-            } else {
-                is_stdout_tty()
-            }
-        });
+    /// The elapsed-time clock starts here.
+    pub fn start(mut self) -> ProgressBar {
+        let target = std::mem::take(&mut self.target);
+        if let DrawTarget::Hidden = target {
+            return self.start_hidden();
+        }
+        let (writer, stream) = target.into_parts();
+        let is_tty = self
+            .tty_override
+            .unwrap_or_else(|| stream.is_some_and(term::can_redraw));
+        self.start_with(Output::Writer(writer), stream, is_tty)
+    }
+
+    /// Start without drawing anything.
+    fn start_hidden(self) -> ProgressBar {
+        self.start_with(Output::Hidden, None, false)
+    }
+
+    /// Start as a child of a [`MultiProgress`].
+    fn start_in(self, slot: Slot) -> ProgressBar {
+        let is_tty = slot.is_tty();
+        self.start_with(Output::Multi(slot), None, is_tty)
+    }
 
+    fn start_with(self, output: Output, stream: Option<Stream>, is_tty: bool) -> ProgressBar {
+        let total = self.total.map(|total| total.max(1));
+        let color = match self.color {
+            ColorChoice::Auto => term::color_level(is_tty),
+            ColorChoice::Always => term::color_depth(),
+            ColorChoice::Never => ColorLevel::None,
+        };
+        if self.restore_cursor {
+            term::install_cursor_guard();
+        }
+        let counter = Arc::new(Counter::new(total, self.clock.clone()));
+        if let Output::Hidden = output {
+            counter.next_draw_at(None);
+        }
+        let started = self.clock.now();
         let mut state = ProgressBarState {
             current: 0,
             total,
+            counter: Arc::clone(&counter),
+            prefix: self.prefix,
             message: self.message,
-            finished: false,
-            writer,
+            outcome: Outcome::InProgress,
+            output,
+            stream,
             config: self.config,
             is_tty,
+            color,
+            clock: self.clock,
+            started,
+            estimator: Estimator::new(started),
+            frame: 0,
+            last_draw: None,
+            last_percent: 0,
+            dirty: false,
+            ticker: None,
+            cursor_hidden: false,
+            cursor_epoch: 0,
         };
         state.render();
 
         ProgressBar {
             state: Arc::new(Mutex::new(state)),
+            counter,
         }
     }
 }
@@ -238,10 +1207,11 @@ impl ProgressBarBuilder {
 /// ```
 ///
 /// `ProgressBar` is `Clone`, `Send`, and `Sync` — clone it to share across threads.
-/// When the last reference is dropped without finalization, a newline is written
-/// to leave the terminal in a clean state.
+/// When the last reference is dropped without finalization, the bar ends as
+/// its [`OnDrop`] policy says, leaving the terminal in a clean state.
 pub struct ProgressBar {
     state: Arc<Mutex<ProgressBarState>>,
+    counter: Arc<Counter>,
 }
 
 impl ProgressBar {
@@ -249,291 +1219,2134 @@ impl ProgressBar {
     /// A total of 0 is normalized to 1 to avoid division by zero.
     #[allow(clippy::new_ret_no_self)]
     pub fn new(total: u64) -> ProgressBarBuilder {
+        Self::builder(Some(total))
+    }
+
+    /// Create a builder for a spinner, for work whose size isn't known up
+    /// front. Spinners show a cycling frame, the running count and the
+    /// message, and finalize through [`success`](ProgressBar::success) and
+    /// [`fail`](ProgressBar::fail) like any other bar.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    ///
+    /// let bar = ProgressBar::spinner().message("Scanning...").start();
+    /// bar.tick(1);
+    /// bar.success("Scanned 1 file");
+    /// ```
+    pub fn spinner() -> ProgressBarBuilder {
+        let mut builder = Self::builder(None);
+        builder.config.template = Template::spinner();
+        builder
+    }
+
+    /// Create a started bar that never draws anything, for quiet modes. It
+    /// counts like any other bar, so timing and ETA still work, at close to
+    /// no cost. Shorthand for
+    /// `ProgressBar::new(total).draw_target(DrawTarget::Hidden).start()`.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    ///
+    /// # let quiet = true;
+    /// let bar = if quiet {
+    ///     ProgressBar::hidden(100)
+    /// } else {
+    ///     ProgressBar::new(100).message("Working...").start()
+    /// };
+    /// bar.tick(1);
+    /// bar.success("Done");
+    /// ```
+    pub fn hidden(total: u64) -> ProgressBar {
+        Self::new(total).draw_target(DrawTarget::Hidden).start()
+    }
+
+    /// Start a bar on stdout sized to `file`, counting in
+    /// [binary bytes](Units::BinaryBytes), and wrap the file in a
+    /// [`ProgressReader`] that ticks it. If the size isn't known, e.g. for a
+    /// pipe, the bar is a spinner. To configure the bar, build it yourself and
+    /// use [`ProgressReader::new`].
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    /// use std::fs::File;
+    /// use std::io;
+    ///
+    /// let mut reader = ProgressBar::wrap_read(File::open("archive.tar")?);
+    /// io::copy(&mut reader, &mut io::sink())?;
+    /// reader.bar().success("Read archive.tar");
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn wrap_read(file: std::fs::File) -> ProgressReader<std::fs::File> {
+        let bar = wrap::builder_for(&file).start();
+        ProgressReader::new(file, bar)
+    }
+
+    fn builder(total: Option<u64>) -> ProgressBarBuilder {
         ProgressBarBuilder {
             total,
             config: BarConfig::default(),
+            prefix: String::new(),
             message: String::new(),
-            writer: None,
+            target: DrawTarget::default(),
             tty_override: None,
+            color: ColorChoice::default(),
+            restore_cursor: false,
+            clock: Clock::default(),
         }
     }
 
-    /// Increment progress by `amount`, clamped to the total. Re-renders the bar.
+    /// Increment progress by `amount`, clamped to the length. Re-renders the bar,
+    /// unless the builder's redraw interval hasn't passed since the last draw.
+    /// Spinners have no total and only advance the count and frame.
     /// No-op if the bar has been finalized.
+    ///
+    /// Ticks don't wait for each other: the count is updated without locking,
+    /// and when another thread holds the bar's lock, this tick leaves the
+    /// drawing to it, to do once it lets go.
     pub fn tick(&self, amount: u64) {
-        let mut s = self.state.lock().unwrap();
-        if s.finished {
+        if self.counter.update(|pos| pos.saturating_add(amount)) {
+            self.redraw();
+        }
+    }
+
+    /// Decrement progress by `amount`, stopping at 0. Redraws like
+    /// [`tick`](ProgressBar::tick).
+    pub fn dec(&self, amount: u64) {
+        if self.counter.update(|pos| pos.saturating_sub(amount)) {
+            self.redraw();
+        }
+    }
+
+    /// Set progress to `position`, clamped to the length. Redraws like
+    /// [`tick`](ProgressBar::tick).
+    pub fn set_position(&self, position: u64) {
+        if self.counter.update(|_| position) {
+            self.redraw();
+        }
+    }
+
+    /// Set the length. As with [`new`](ProgressBar::new), 0 becomes 1, and
+    /// the position is clamped if it is now past the end.
+    ///
+    /// A spinner becomes a bar. Unless it was given a
+    /// [`template`](ProgressBarBuilder::template), it switches to the default
+    /// bar template; a custom one starts rendering length-relative fields
+    /// such as `{bar}` and `{percent}`.
+    pub fn set_length(&self, length: u64) {
+        let mut s = self.lock();
+        if !s.is_finished() {
+            s.set_length(length);
+        }
+    }
+
+    /// Grow the length by `amount`, for work discovered along the way. A
+    /// spinner gets a length of `amount` and becomes a bar as with
+    /// [`set_length`](ProgressBar::set_length), so crawlers can start as
+    /// spinners and grow as they find work. Growing by 0 does nothing.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    ///
+    /// let bar = ProgressBar::spinner().start();
+    /// bar.inc_length(3); // found three files
+    /// bar.tick(1);
+    /// bar.inc_length(2); // one of them was a directory with two more
+    /// ```
+    pub fn inc_length(&self, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        let mut s = self.lock();
+        if !s.is_finished() {
+            let length = s.total.map_or(amount, |total| total.saturating_add(amount));
+            s.set_length(length);
+        }
+    }
+
+    /// Set the position back to 0 and restart the clock, so elapsed time,
+    /// rate and ETA start over. The length, message and prefix are kept, and
+    /// the bar is redrawn immediately. No-op once finalized.
+    pub fn reset(&self) {
+        let mut s = self.lock();
+        if !self.counter.update(|_| 0) {
             return;
         }
-        s.current = s.current.saturating_add(amount).min(s.total);
+        s.sync();
+        let now = s.clock.now();
+        s.started = now;
+        s.estimator = Estimator::new(now);
         s.render();
     }
 
-    /// Update the displayed message. Takes effect on the next render.
-    pub fn set_message(&self, msg: &str) {
-        let mut s = self.state.lock().unwrap();
-        s.message = msg.to_string();
+    /// Current position.
+    pub fn position(&self) -> u64 {
+        self.counter.position()
     }
 
-    /// Finalize with a green `✔` and the given message. Stops further ticks.
-    pub fn success(&self, msg: &str) {
-        let mut s = self.state.lock().unwrap();
-        s.finalize("✔", "\x1b[32m", msg);
+    /// Length, or `None` for a spinner.
+    pub fn length(&self) -> Option<u64> {
+        self.counter.length()
     }
 
-    /// Finalize with a red `✖` and the given message. Stops further ticks.
-    pub fn fail(&self, msg: &str) {
-        let mut s = self.state.lock().unwrap();
-        s.finalize("✖", "\x1b[31m", msg);
+    /// The message currently shown.
+    pub fn message(&self) -> String {
+        self.lock().message.clone()
     }
-}
 
-impl Clone for ProgressBar {
+    /// Whether the bar has been finalized.
+    pub fn is_finished(&self) -> bool {
+        self.counter.is_finished()
+    }
+
+    /// Position, length, timing and outcome, read together under one lock so
+    /// they are consistent with each other.
+    ///
+    /// ```no_run
+    /// use nanoprogress::{Outcome, ProgressBar};
+    ///
+    /// let bar = ProgressBar::new(100).start();
+    /// bar.tick(30);
+    /// let state = bar.snapshot();
+    /// if state.outcome == Outcome::InProgress && state.position * 2 < 100 {
+    ///     println!("{} left, eta {:?}", 100 - state.position, state.eta);
+    /// }
+    /// ```
+    pub fn snapshot(&self) -> ProgressState {
+        let s = self.lock();
+        ProgressState {
+            position: s.current,
+            length: s.total,
+            elapsed: s.elapsed(),
+            eta: s.eta(),
+            per_sec: s.per_sec(),
+            outcome: s.outcome,
+        }
+    }
+
+    /// Time since the bar was started.
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed()
+    }
+
+    /// Estimated time remaining, based on the smoothed rate. `None` until
+    /// there is any progress to extrapolate from.
+    pub fn eta(&self) -> Option<Duration> {
+        self.lock().eta()
+    }
+
+    /// Smoothed throughput in items per second.
+    pub fn per_sec(&self) -> f64 {
+        self.lock().per_sec()
+    }
+
+    /// Update the displayed message. Takes effect on the next render.
+    pub fn set_message(&self, msg: &str) {
+        let mut s = self.lock();
+        s.message = msg.to_string();
+    }
+
+    /// Update the displayed prefix. Takes effect on the next render.
+    pub fn set_prefix(&self, prefix: &str) {
+        let mut s = self.lock();
+        s.prefix = prefix.to_string();
+    }
+
+    /// Redraw the bar every `interval` from a background thread, so spinners
+    /// keep turning and elapsed time and ETA stay current while the work is
+    /// blocked and not ticking. When not on a terminal, the thread only
+    /// draws progress that ticks haven't drawn yet, following the
+    /// [`NonTtyPolicy`].
+    ///
+    /// The thread stops when the bar is finalized or its last clone is
+    /// dropped. Calling this again replaces the interval; a zero interval
+    /// stops the thread like
+    /// [`disable_steady_tick`](ProgressBar::disable_steady_tick). Hidden bars
+    /// don't start one.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// let bar = ProgressBar::spinner().message("Waiting for server...").start();
+    /// bar.enable_steady_tick(Duration::from_millis(100));
+    /// // a long blocking call
+    /// bar.success("Connected");
+    /// ```
+    pub fn enable_steady_tick(&self, interval: Duration) {
+        if interval.is_zero() {
+            return self.disable_steady_tick();
+        }
+        let mut s = self.lock();
+        if s.is_finished() || matches!(s.output, Output::Hidden) {
+            return;
+        }
+        let state = Arc::downgrade(&self.state);
+        let spawned = thread::Builder::new()
+            .name("nanoprogress-tick".to_string())
+            .spawn(move || steady_tick(state, interval));
+        if let Ok(handle) = spawned {
+            if let Some(previous) = s.ticker.replace(handle.thread().clone()) {
+                previous.unpark();
+            }
+        }
+    }
+
+    /// Stop the thread started by
+    /// [`enable_steady_tick`](ProgressBar::enable_steady_tick), if any.
+    pub fn disable_steady_tick(&self) {
+        if let Some(ticker) = self.lock().ticker.take() {
+            ticker.unpark();
+        }
+    }
+
+    /// Print a line above the bar without corrupting it.
+    ///
+    /// In TTY mode the bar's line is cleared, `text` is written in its place
+    /// and the bar is redrawn underneath. The state lock is held throughout,
+    /// so output from other threads using this bar can't interleave.
+    pub fn println(&self, text: &str) {
+        self.lock().println(text);
+    }
+
+    /// Clear the bar, run `f`, and redraw the bar underneath whatever `f`
+    /// printed. Useful for output that doesn't go through
+    /// [`println`](ProgressBar::println), such as logging or child processes.
+    ///
+    /// The state lock is held while `f` runs, so `f` must not use this bar
+    /// (or its clones), or it will deadlock.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    ///
+    /// let bar = ProgressBar::new(10).start();
+    /// bar.suspend(|| eprintln!("warning: skipped a file"));
+    /// ```
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.lock().suspend(f)
+    }
+
+    /// Finalize, leaving the bar showing its final state. Stops further ticks.
+    pub fn finish(&self) {
+        self.lock().finish();
+    }
+
+    /// Finalize with a green `✔` and the given message. Stops further ticks.
+    pub fn success(&self, msg: &str) {
+        self.end(Outcome::Succeeded, "✔", |theme| theme.success, msg);
+    }
+
+    /// Finalize with a red `✖` and the given message. Stops further ticks.
+    pub fn fail(&self, msg: &str) {
+        self.end(Outcome::Failed, "✖", |theme| theme.fail, msg);
+    }
+
+    /// Finalize with a yellow `⚠` and the given message, for work that
+    /// finished with problems. Stops further ticks.
+    pub fn warn(&self, msg: &str) {
+        self.end(Outcome::Warned, "⚠", |theme| theme.warning, msg);
+    }
+
+    /// Finalize with a blue `ℹ` and the given message. Stops further ticks.
+    pub fn info(&self, msg: &str) {
+        self.end(Outcome::Info, "ℹ", |theme| theme.info, msg);
+    }
+
+    /// Finalize with a dimmed `↓` and the given message, for work that was
+    /// skipped. Stops further ticks.
+    pub fn skip(&self, msg: &str) {
+        self.end(Outcome::Skipped, "↓", |theme| theme.skip, msg);
+    }
+
+    /// Finalize with a custom symbol, styled with `style`, and the given
+    /// message. Stops further ticks.
+    ///
+    /// ```no_run
+    /// use nanoprogress::{Color, ProgressBar, Style};
+    ///
+    /// let bar = ProgressBar::new(10).start();
+    /// bar.finish_with("♻", Style::new().fg(Color::Cyan), "Restored from cache");
+    /// ```
+    pub fn finish_with(&self, symbol: &str, style: Style, msg: &str) {
+        self.end(Outcome::Custom, symbol, |_| style, msg);
+    }
+
+    /// Finalize by erasing the bar, whatever
+    /// [`clear_on_finish`](ProgressBarBuilder::clear_on_finish) says. When
+    /// not on a terminal, the lines already printed stay. Stops further ticks.
+    pub fn finish_and_clear(&self) {
+        self.lock().finish_and_clear();
+    }
+
+    /// Stop the bar where it is, leaving it drawn as it was, and move later
+    /// output below it. By default this is also what happens when the last
+    /// clone of an unfinished bar is dropped (see [`OnDrop`]). Stops further
+    /// ticks.
+    pub fn abandon(&self) {
+        self.lock().abandon();
+    }
+
+    /// Finalize from the outcome of the work: [`success`](ProgressBar::success)
+    /// with the bar's current message on `Ok`, [`fail`](ProgressBar::fail)
+    /// with the error on `Err`.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    /// use std::fs;
+    ///
+    /// let bar = ProgressBar::spinner().message("Read config").start();
+    /// let config = fs::read_to_string("config.toml");
+    /// bar.finish_with_result(&config); // ✔ Read config, or ✖ No such file …
+    /// ```
+    pub fn finish_with_result<T, E: Display>(&self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => {
+                let msg = self.message();
+                self.success(&msg);
+            }
+            Err(err) => self.fail(&err.to_string()),
+        }
+    }
+
+    /// Run `f` with this bar and finalize it from the result, as
+    /// [`finish_with_result`](ProgressBar::finish_with_result) does, unless
+    /// `f` finalized it already. If `f` panics, the bar fails with the panic
+    /// message and the panic carries on.
+    ///
+    /// ```no_run
+    /// use nanoprogress::ProgressBar;
+    /// use std::io;
+    ///
+    /// let bar = ProgressBar::new(3).message("Uploaded").start();
+    /// let sent = bar.run(|bar| {
+    ///     for _ in 0..3 {
+    ///         // upload a chunk, returning early with `?` on failure
+    ///         bar.tick(1);
+    ///     }
+    ///     Ok::<_, io::Error>(3)
+    /// })?;
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn run<T, E: Display>(&self, f: impl FnOnce(&ProgressBar) -> Result<T, E>) -> Result<T, E> {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => {
+                self.finish_with_result(&result);
+                result
+            }
+            Err(payload) => {
+                let msg = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("panicked");
+                self.fail(msg);
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Lock the state, up to date with the counter. Ticks that find it
+    /// locked are drawn when the returned guard is dropped.
+    ///
+    /// The lock is poisoned if a closure passed to
+    /// [`suspend`](ProgressBar::suspend) panics, but the state is never left
+    /// half-updated, so the bar carries on.
+    fn lock(&self) -> StateGuard<'_> {
+        let mut s = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        s.sync();
+        StateGuard {
+            state: Some(s),
+            lock: &self.state,
+            counter: &self.counter,
+        }
+    }
+
+    /// Redraw after a tick if it's due. If another thread holds the lock,
+    /// the tick is left for it to draw once it lets go.
+    fn redraw(&self) {
+        if self.counter.draw_due(self.counter.now()) {
+            self.counter.request_draw();
+            draw_requested(&self.state, &self.counter);
+        }
+    }
+
+    fn end(&self, outcome: Outcome, symbol: &str, style: impl FnOnce(&Theme) -> Style, msg: &str) {
+        let mut s = self.lock();
+        let style = style(&s.config.theme);
+        s.finalize(outcome, symbol, style, msg);
+    }
+
+    fn slot_id(&self, multi: &Arc<Mutex<MultiState>>) -> Option<usize> {
+        match &self.lock().output {
+            Output::Multi(slot) => slot.id_in(multi),
+            Output::Writer(_) | Output::Hidden => None,
+        }
+    }
+}
+
+impl Clone for ProgressBar {
     fn clone(&self) -> Self {
+        self.counter.add_handle();
         ProgressBar {
             state: Arc::clone(&self.state),
+            counter: Arc::clone(&self.counter),
+        }
+    }
+}
+
+/// The locked state of a bar. Dropping it unlocks the state and draws any
+/// ticks that arrived in the meantime.
+struct StateGuard<'a> {
+    state: Option<MutexGuard<'a, ProgressBarState>>,
+    lock: &'a Mutex<ProgressBarState>,
+    counter: &'a Counter,
+}
+
+impl Deref for StateGuard<'_> {
+    type Target = ProgressBarState;
+
+    fn deref(&self) -> &ProgressBarState {
+        self.state.as_ref().expect("state is locked until drop")
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut ProgressBarState {
+        self.state.as_mut().expect("state is locked until drop")
+    }
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        self.state = None;
+        draw_requested(self.lock, self.counter);
+    }
+}
+
+/// Draw ticks that asked for a redraw, unless another thread holds the lock.
+/// Every holder calls this after unlocking, so a tick that found the lock
+/// taken is drawn by the thread that had it, if not by itself.
+fn draw_requested(lock: &Mutex<ProgressBarState>, counter: &Counter) {
+    while counter.draw_requested() {
+        let mut s = match lock.try_lock() {
+            Ok(s) => s,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        if counter.take_draw_request() && !s.is_finished() {
+            s.sync();
+            let now = s.clock.now();
+            s.redraw(now);
+        }
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        if self.counter.drop_handle() {
+            let mut s = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            s.sync();
+            s.drop_unfinished();
+        }
+    }
+}
+
+/// Body of a steady-tick thread: redraw every `interval` until the bar is
+/// finalized or dropped, or the thread is replaced or stopped. It holds only
+/// a weak reference between draws, so it never keeps a dropped bar alive.
+fn steady_tick(state: Weak<Mutex<ProgressBarState>>, interval: Duration) {
+    loop {
+        thread::park_timeout(interval);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let mut s = state.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = s
+            .ticker
+            .as_ref()
+            .is_some_and(|ticker| ticker.id() == thread::current().id());
+        if !current || s.is_finished() {
+            return;
+        }
+        s.sync();
+        if s.is_tty {
+            s.render();
+        } else if s.dirty {
+            let now = s.clock.now();
+            s.redraw(now);
+        }
+        let counter = Arc::clone(&s.counter);
+        drop(s);
+        draw_requested(&state, &counter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // --- Task 4.1: Test helper ---
+
+    #[derive(Clone)]
+    pub(crate) struct TestWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl TestWriter {
+        fn new() -> Self {
+            TestWriter(Arc::new(Mutex::new(Vec::new())))
+        }
+
+        pub(crate) fn output(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+        }
+    }
+
+    impl io::Write for TestWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn make_writer() -> (TestWriter, Box<dyn Write + Send>) {
+        let tw = TestWriter::new();
+        let boxed: Box<dyn Write + Send> = Box::new(tw.clone());
+        (tw, boxed)
+    }
+
+    // --- Task 4.2: Builder defaults and custom writer ---
+
+    #[test]
+    fn test_builder_defaults() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(100).writer(w).start();
+        let out = tw.output();
+        // Default bar: 40 chars of empty char, 0%, 0/100
+        assert!(
+            out.contains("0% 0/100"),
+            "expected default render, got: {out}"
+        );
+    }
+
+    #[test]
+    fn test_custom_writer_receives_output() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).start();
+        bar.tick(5);
+        let out = tw.output();
+        assert!(!out.is_empty(), "custom writer should receive output");
+        assert!(out.contains("5/10"));
+    }
+
+    #[test]
+    fn test_start_produces_initial_render() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(50).writer(w).start();
+        let out = tw.output();
+        assert!(out.contains("0/50"), "start should render initial state");
+    }
+
+    // --- Task 4.3: TTY vs non-TTY rendering ---
+
+    #[test]
+    fn test_tty_mode_uses_cr() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10).writer(w).tty(true).start();
+        let out = tw.output();
+        assert!(
+            out.starts_with("\x1b[?25l\r"),
+            "TTY mode should hide the cursor and start with \\r, got: {out:?}"
+        );
+    }
+
+    #[test]
+    fn test_non_tty_mode_uses_newlines_no_ansi() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .start(); // custom writer defaults non-TTY
+        bar.tick(5);
+        bar.success("done");
+        let out = tw.output();
+        assert!(!out.contains("\x1b["), "non-TTY should have no ANSI codes");
+        assert!(out.contains('\n'), "non-TTY should use newlines");
+    }
+
+    #[test]
+    fn test_custom_writer_defaults_non_tty() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10).writer(w).start();
+        let out = tw.output();
+        // Non-TTY: no \r prefix, uses newline
+        assert!(
+            !out.starts_with('\r'),
+            "custom writer should default to non-TTY"
+        );
+    }
+
+    #[test]
+    fn test_non_tty_finalization_omits_ansi() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .start();
+        bar.success("all good");
+        let out = tw.output();
+        assert!(
+            !out.contains("\x1b["),
+            "non-TTY finalization should omit ANSI"
+        );
+        assert!(out.contains("✔"));
+        assert!(out.contains("all good"));
+    }
+
+    // --- Task 4.4: Drop behavior ---
+
+    #[test]
+    fn test_drop_without_finalization_writes_newline() {
+        let (tw, w) = make_writer();
+        {
+            let _bar = ProgressBar::new(10).writer(w).start();
+            // dropped here without success/fail
+        }
+        let out = tw.output();
+        assert!(out.ends_with('\n'), "drop should write trailing newline");
+    }
+
+    #[test]
+    fn test_drop_after_finalization_no_extra_output() {
+        let (tw, w) = make_writer();
+        let out_before;
+        {
+            let bar = ProgressBar::new(10).writer(w).start();
+            bar.success("done");
+            out_before = tw.output();
         }
+        let out_after = tw.output();
+        assert_eq!(
+            out_before, out_after,
+            "drop after finalization should add nothing"
+        );
+    }
+
+    // --- Task 4.5: Send + Sync assertion ---
+
+    #[test]
+    fn test_progress_bar_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ProgressBar>();
+    }
+
+    #[test]
+    fn test_fill_and_empty_builder_methods() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).fill('#').empty('-').start();
+        bar.tick(5);
+        let out = tw.output();
+        assert!(out.contains('#'), "custom fill char should appear");
+        assert!(out.contains('-'), "custom empty char should appear");
+    }
+
+    #[test]
+    fn test_set_message_updates_output() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).start();
+        bar.set_message("hello");
+        bar.tick(1);
+        let out = tw.output();
+        assert!(
+            out.contains("hello"),
+            "set_message should update displayed text"
+        );
+    }
+
+    #[test]
+    fn test_tty_finalize_success_has_ansi() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Always)
+            .start();
+        bar.success("completed");
+        let out = tw.output();
+        assert!(
+            out.contains("\x1b[32m"),
+            "TTY success should have green ANSI code"
+        );
+        assert!(out.contains("✔"), "TTY success should have checkmark");
+        assert!(out.contains("completed"));
+    }
+
+    #[test]
+    fn test_tty_finalize_fail_has_ansi() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Always)
+            .start();
+        bar.fail("broken");
+        let out = tw.output();
+        assert!(
+            out.contains("\x1b[31m"),
+            "TTY fail should have red ANSI code"
+        );
+        assert!(out.contains("✖"), "TTY fail should have cross mark");
+        assert!(out.contains("broken"));
+    }
+
+    #[test]
+    fn test_clear_on_finish_erases_line() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .clear_on_finish(true)
+            .start();
+        bar.success("gone");
+        let out = tw.output();
+        assert!(out.ends_with("\r\x1b[2K"), "got: {out:?}");
+        assert!(!out.contains("gone"));
+    }
+
+    #[test]
+    fn test_double_finalization_is_noop() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).tty(true).start();
+        bar.success("first");
+        let out_after_first = tw.output();
+        bar.fail("second");
+        let out_after_second = tw.output();
+        assert_eq!(
+            out_after_first, out_after_second,
+            "double finalization should be a no-op"
+        );
+    }
+
+    #[test]
+    fn test_total_zero_normalized_to_one() {
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::new(0).writer(w).start();
+        assert_eq!(
+            bar.length(),
+            Some(1),
+            "total of 0 should be normalized to 1"
+        );
+    }
+
+    #[test]
+    fn test_draw_target_writer_matches_writer() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .draw_target(DrawTarget::Writer(w))
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(2);
+        assert_eq!(tw.output(), "0\n2\n");
+    }
+
+    #[test]
+    fn test_hidden_target_keeps_counting() {
+        let bar = ProgressBar::new(10).draw_target(DrawTarget::Hidden).start();
+        bar.tick(4);
+        assert_eq!(bar.position(), 4);
+        assert!(!bar.state.lock().unwrap().is_tty);
+    }
+
+    #[test]
+    fn test_hidden_bar_never_renders() {
+        let (clock, time) = Clock::manual();
+        let bar = ProgressBar::new(100)
+            .tty(true)
+            .clock(clock)
+            .draw_target(DrawTarget::Hidden)
+            .start();
+        for _ in 0..10 {
+            time.advance(Duration::from_secs(1));
+            bar.tick(5);
+        }
+        assert_eq!(bar.eta(), Some(Duration::from_secs(10)));
+        bar.println("quiet");
+        assert_eq!(bar.suspend(|| 7), 7);
+        bar.success("done");
+        assert!(bar.is_finished());
+        let s = bar.state.lock().unwrap();
+        assert_eq!(s.last_draw, None, "a hidden bar should never format a line");
+    }
+
+    #[test]
+    fn test_finish_keeps_final_state_on_tty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.tick(10);
+        bar.finish();
+        bar.tick(1);
+        assert_eq!(
+            tw.output(),
+            "\x1b[?25l\r0/10\r10/10\x1b[?25h\r\x1b[2K10/10\n"
+        );
+    }
+
+    #[test]
+    fn test_finish_non_tty_prints_nothing_extra() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.tick(4);
+        bar.finish();
+        drop(bar);
+        assert_eq!(tw.output(), "0/10\n4/10\n");
+    }
+
+    // --- Position and length ---
+
+    #[test]
+    fn test_getters_and_snapshot() {
+        let (clock, time) = Clock::manual();
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::new(100)
+            .writer(w)
+            .clock(clock)
+            .message("working")
+            .start();
+        time.advance(Duration::from_secs(4));
+        bar.tick(40);
+        assert_eq!(bar.position(), 40);
+        assert_eq!(bar.length(), Some(100));
+        assert_eq!(bar.message(), "working");
+        assert!(!bar.is_finished());
+
+        let state = bar.snapshot();
+        assert_eq!(state.position, 40);
+        assert_eq!(state.length, Some(100));
+        assert_eq!(state.elapsed, Duration::from_secs(4));
+        assert_eq!(state.eta, Some(Duration::from_secs(6)));
+        assert_eq!(state.per_sec, 10.0);
+        assert_eq!(state.outcome, Outcome::InProgress);
+
+        bar.fail("stopped");
+        assert!(bar.is_finished());
+        assert_eq!(bar.snapshot().outcome, Outcome::Failed);
+    }
+
+    #[test]
+    fn test_outcome_of_success_and_finish() {
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::new(1).writer(w).start();
+        bar.success("ok");
+        assert_eq!(bar.snapshot().outcome, Outcome::Succeeded);
+
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::spinner().writer(w).start();
+        bar.finish();
+        assert_eq!(bar.snapshot().outcome, Outcome::Finished);
+    }
+
+    #[test]
+    fn test_set_position_and_dec() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.set_position(7);
+        bar.dec(3);
+        bar.dec(10);
+        bar.set_position(99);
+        assert_eq!(tw.output(), "0/10\n7/10\n4/10\n0/10\n10/10\n");
+    }
+
+    #[test]
+    fn test_set_length_clamps_and_normalizes() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.tick(8);
+        bar.set_length(5);
+        bar.set_length(0);
+        bar.inc_length(4);
+        assert_eq!(tw.output(), "0/10\n8/10\n5/5\n1/1\n1/5\n");
+    }
+
+    #[test]
+    fn test_inc_length_turns_spinner_into_bar() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .template("{pos}/{len} {percent}%")
+            .unwrap()
+            .start();
+        bar.tick(1);
+        bar.inc_length(4);
+        bar.inc_length(4);
+        assert_eq!(tw.output(), "0/ %\n1/ %\n1/4 25%\n1/8 12%\n");
+    }
+
+    #[test]
+    fn test_spinner_with_length_switches_to_bar_template() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .color(ColorChoice::Never)
+            .width(4)
+            .frames(&["*"])
+            .start();
+        bar.tick(1);
+        bar.inc_length(0);
+        assert_eq!(bar.length(), None);
+        bar.inc_length(4);
+        assert_eq!(tw.output(), "* 0\n* 1\n[█░░░]  25% 1/4\n");
+    }
+
+    #[test]
+    fn test_reset_restarts_clock() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(100)
+            .writer(w)
+            .clock(clock)
+            .template("{pos} {elapsed}")
+            .unwrap()
+            .start();
+        time.advance(Duration::from_secs(5));
+        bar.tick(50);
+        bar.reset();
+        assert_eq!(bar.elapsed(), Duration::ZERO);
+        assert_eq!(bar.eta(), None);
+        time.advance(Duration::from_secs(2));
+        bar.tick(10);
+        assert_eq!(bar.per_sec(), 5.0);
+        assert_eq!(
+            tw.output(),
+            "0 00:00:00\n50 00:00:05\n0 00:00:00\n10 00:00:02\n"
+        );
+    }
+
+    #[test]
+    fn test_reset_after_inc_length_keeps_length() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .clock(clock)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.inc_length(4);
+        time.advance(Duration::from_secs(1));
+        bar.tick(3);
+        bar.reset();
+        assert_eq!((bar.position(), bar.length()), (0, Some(4)));
+        assert_eq!(bar.eta(), None);
+        assert_eq!(bar.per_sec(), 0.0);
+        bar.inc_length(2);
+        bar.tick(7);
+        assert_eq!(bar.position(), 6);
+        assert_eq!(tw.output(), "0/\n0/4\n3/4\n0/4\n0/6\n6/6\n");
+    }
+
+    #[test]
+    fn test_position_changes_ignored_after_finish() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).start();
+        bar.success("done");
+        let before = tw.output();
+        bar.set_position(3);
+        bar.set_length(20);
+        bar.dec(1);
+        bar.reset();
+        assert_eq!(tw.output(), before);
+        assert_eq!(bar.length(), Some(10));
+    }
+
+    // --- Finalization outcomes ---
+
+    #[test]
+    fn test_warn_info_and_skip_symbols() {
+        for (finish, expected, outcome) in [
+            (
+                ProgressBar::warn as fn(&ProgressBar, &str),
+                "\x1b[33m⚠\x1b[0m x\n",
+                Outcome::Warned,
+            ),
+            (ProgressBar::info, "\x1b[34mℹ\x1b[0m x\n", Outcome::Info),
+            (ProgressBar::skip, "\x1b[2m↓\x1b[0m x\n", Outcome::Skipped),
+        ] {
+            let (tw, w) = make_writer();
+            let bar = ProgressBar::new(10)
+                .writer(w)
+                .tty(true)
+                .color(ColorChoice::Always)
+                .start();
+            finish(&bar, "x");
+            assert!(tw.output().ends_with(expected), "got: {:?}", tw.output());
+            assert_eq!(bar.snapshot().outcome, outcome);
+        }
+    }
+
+    #[test]
+    fn test_finish_with_custom_symbol() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.finish_with("♻", Style::new().fg(Color::Cyan), "cached");
+        assert_eq!(tw.output(), "0\n♻ cached\n");
+        assert_eq!(bar.snapshot().outcome, Outcome::Custom);
+    }
+
+    #[test]
+    fn test_finish_and_clear_erases_line() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(2);
+        bar.finish_and_clear();
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r2\x1b[?25h\r\x1b[2K");
+        assert_eq!(bar.snapshot().outcome, Outcome::Cleared);
+
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.finish_and_clear();
+        assert_eq!(tw.output(), "0\n");
+    }
+
+    #[test]
+    fn test_abandon_leaves_bar_drawn() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(4);
+        bar.abandon();
+        bar.tick(1);
+        bar.success("ignored");
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r4\x1b[?25h\r\x1b[2K4\n");
+        assert_eq!(bar.snapshot().outcome, Outcome::Abandoned);
+
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.abandon();
+        assert_eq!(tw.output(), "0\n");
+    }
+
+    // --- Templates ---
+
+    #[test]
+    fn test_template_rearranges_fields() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .width(4)
+            .prefix("dl")
+            .message("file.bin")
+            .template("{prefix} {msg} {pos}/{len} {percent}% {bar}")
+            .unwrap()
+            .start();
+        bar.tick(5);
+        let out = tw.output();
+        assert!(
+            out.contains("dl file.bin 5/10 50% ██░░"),
+            "template should control layout, got: {out}"
+        );
+    }
+
+    #[test]
+    fn test_template_can_drop_fields() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos:>3}/{len}")
+            .unwrap()
+            .start();
+        bar.tick(7);
+        let out = tw.output();
+        assert_eq!(out, "  0/10\n  7/10\n");
+    }
+
+    #[test]
+    fn test_template_error_from_builder() {
+        let result = ProgressBar::new(10).template("{bar} {eta_typo}");
+        assert_eq!(
+            result.err(),
+            Some(TemplateError::UnknownPlaceholder("eta_typo".into()))
+        );
+    }
+
+    #[test]
+    fn test_set_prefix_updates_output() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{prefix}: {pos}")
+            .unwrap()
+            .start();
+        bar.set_prefix("step 2");
+        bar.tick(1);
+        assert!(tw.output().ends_with("step 2: 1\n"));
+    }
+
+    // --- Timing ---
+
+    #[test]
+    fn test_elapsed_eta_and_rate_fields() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(100)
+            .writer(w)
+            .clock(clock)
+            .template("{elapsed} {eta} {per_sec}")
+            .unwrap()
+            .start();
+        assert!(tw.output().starts_with("00:00:00 --:--:-- 0.0/s\n"));
+        for _ in 0..10 {
+            time.advance(Duration::from_secs(1));
+            bar.tick(2);
+        }
+        assert_eq!(bar.elapsed(), Duration::from_secs(10));
+        assert_eq!(bar.per_sec(), 2.0);
+        assert_eq!(bar.eta(), Some(Duration::from_secs(40)));
+        assert!(tw.output().ends_with("00:00:10 00:00:40 2.0/s\n"));
+    }
+
+    #[test]
+    fn test_units_apply_to_pos_len_and_rate() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(4 << 30)
+            .writer(w)
+            .clock(clock)
+            .units(Units::BinaryBytes)
+            .human_durations(true)
+            .template("{pos}/{len} {per_sec} {elapsed} {eta}")
+            .unwrap()
+            .start();
+        time.advance(Duration::from_secs(8));
+        bar.tick(1 << 30);
+        assert_eq!(
+            tw.output(),
+            "0 B/4.0 GiB 0.0 B/s 0s ?\n1.0 GiB/4.0 GiB 128.0 MiB/s 8s 24s\n"
+        );
+    }
+
+    #[test]
+    fn test_eta_unknown_before_progress() {
+        let (clock, time) = Clock::manual();
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::new(100).writer(w).clock(clock).start();
+        time.advance(Duration::from_secs(5));
+        assert_eq!(bar.eta(), None);
+        assert_eq!(bar.per_sec(), 0.0);
+    }
+
+    // --- Spinner mode ---
+
+    #[test]
+    fn test_spinner_renders_frame_count_and_message() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner().writer(w).message("scanning").start();
+        bar.tick(3);
+        let out = tw.output();
+        assert_eq!(out, "⠋ 0 scanning\n⠙ 3 scanning\n");
+        assert!(!out.contains('%'), "spinner should not show a percent");
+    }
+
+    #[test]
+    fn test_spinner_custom_frames_cycle() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .frames(&["-", "+"])
+            .template("{spinner}")
+            .unwrap()
+            .start();
+        bar.tick(1);
+        bar.tick(1);
+        assert_eq!(tw.output(), "-\n+\n-\n");
+    }
+
+    #[test]
+    fn test_spinner_count_is_unbounded() {
+        let (_tw, w) = make_writer();
+        let bar = ProgressBar::spinner().writer(w).start();
+        bar.tick(1_000_000);
+        bar.tick(1);
+        assert_eq!(bar.position(), 1_000_001);
+        assert_eq!(bar.length(), None);
+    }
+
+    #[test]
+    fn test_spinner_renders_length_fields_empty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .template("[{eta}] [{len}] [{percent}] {pos}")
+            .unwrap()
+            .frames(&["*"])
+            .start();
+        bar.tick(2);
+        assert_eq!(tw.output(), "[] [] [] 0\n[] [] [] 2\n");
+    }
+
+    #[test]
+    fn test_spinner_finalizes_like_bar() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Always)
+            .start();
+        bar.tick(1);
+        bar.fail("gave up");
+        let out = tw.output();
+        assert!(out.contains("\x1b[31m✖\x1b[0m gave up"));
+        assert!(bar.eta().is_none());
+    }
+
+    // --- Redraw rate limiting ---
+
+    #[test]
+    fn test_redraw_interval_skips_intermediate_ticks() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(1000)
+            .writer(w)
+            .clock(clock)
+            .redraw_interval(Duration::from_millis(100))
+            .template("{pos}")
+            .unwrap()
+            .start();
+        for _ in 0..500 {
+            time.advance(Duration::from_millis(1));
+            bar.tick(1);
+        }
+        assert_eq!(bar.position(), 500);
+        assert_eq!(tw.output(), "0\n100\n200\n300\n400\n500\n");
+    }
+
+    #[test]
+    fn test_finalize_draws_skipped_state() {
+        let (clock, _time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .clock(clock)
+            .max_redraws_per_sec(10)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(3);
+        bar.tick(4);
+        assert_eq!(tw.output(), "0\n");
+        bar.success("done");
+        assert_eq!(tw.output(), "0\n7\n✔ done\n");
+    }
+
+    #[test]
+    fn test_finalize_without_pending_ticks_draws_nothing_extra() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .clock(clock)
+            .max_redraws_per_sec(10)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        time.advance(Duration::from_secs(1));
+        bar.tick(3);
+        bar.success("done");
+        assert_eq!(tw.output(), "0\n3\n✔ done\n");
+    }
+
+    #[test]
+    fn test_max_redraw_interval_draws_only_start_and_finish() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .redraw_interval(Duration::MAX)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(3);
+        bar.tick(4);
+        bar.success("done");
+        assert_eq!(tw.output(), "0\n7\n✔ done\n");
+    }
+
+    // --- Non-TTY policy ---
+
+    #[test]
+    fn test_non_tty_percent_milestones() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(1000)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .non_tty_policy(NonTtyPolicy::Percent(10))
+            .template("{percent}%")
+            .unwrap()
+            .start();
+        for _ in 0..1000 {
+            bar.tick(1);
+        }
+        bar.success("done");
+        let out = tw.output();
+        let expected: String = (0..=100).step_by(10).map(|p| format!("{p}%\n")).collect();
+        assert_eq!(out, expected + "✔ done\n");
+    }
+
+    #[test]
+    fn test_non_tty_interval() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(100)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .clock(clock)
+            .non_tty_policy(NonTtyPolicy::Interval(Duration::from_secs(30)))
+            .template("{pos}")
+            .unwrap()
+            .start();
+        for _ in 0..100 {
+            time.advance(Duration::from_secs(1));
+            bar.tick(1);
+        }
+        assert_eq!(tw.output(), "0\n30\n60\n90\n");
+        bar.success("done");
+        assert!(tw.output().ends_with("90\n100\n✔ done\n"));
+    }
+
+    #[test]
+    fn test_non_tty_start_and_finish() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(50)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .non_tty_policy(NonTtyPolicy::StartAndFinish)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        for _ in 0..50 {
+            bar.tick(1);
+        }
+        bar.fail("stopped");
+        assert_eq!(tw.output(), "0/50\n50/50\n✖ stopped\n");
+    }
+
+    #[test]
+    fn test_non_tty_policy_ignored_on_tty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .non_tty_policy(NonTtyPolicy::StartAndFinish)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.tick(1);
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r1");
+    }
+
+    #[test]
+    fn test_log_timestamps() {
+        let (clock, time) = Clock::manual();
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .clock(clock)
+            .log_timestamps(true)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        time.advance(Duration::from_secs(65));
+        bar.success("done");
+        let out = tw.output();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // e.g. "2024-05-01T12:00:00Z [00:00:00] 0"
+        assert_eq!(lines[0].len(), "2024-05-01T12:00:00Z [00:00:00] 0".len());
+        assert!(lines[0].ends_with("Z [00:00:00] 0"), "got: {out}");
+        assert!(lines[1].ends_with("Z [00:01:05] ✔ done"), "got: {out}");
+    }
+
+    // --- println / suspend ---
+
+    #[test]
+    fn test_println_clears_and_redraws_on_tty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.tick(3);
+        bar.println("hello");
+        assert_eq!(tw.output(), "\x1b[?25l\r0/10\r3/10\r\x1b[2Khello\n\r3/10");
+    }
+
+    #[test]
+    fn test_println_non_tty_writes_plain_line() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}/{len}")
+            .unwrap()
+            .start();
+        bar.println("hello");
+        assert_eq!(tw.output(), "0/10\nhello\n");
+    }
+
+    #[test]
+    fn test_println_after_finish_does_not_redraw() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10).writer(w).tty(true).start();
+        bar.success("done");
+        let before = tw.output();
+        bar.println("after");
+        assert_eq!(tw.output(), before + "after\n");
     }
-}
 
-impl Drop for ProgressBar {
-    fn drop(&mut self) {
-        if Arc::strong_count(&self.state) == 1 {
-            if let Ok(mut s) = self.state.lock() {
-                if !s.finished {
-                    let _ = writeln!(s.writer);
-                    let _ = s.writer.flush();
-                }
-            }
-        }
+    #[test]
+    fn test_suspend_runs_closure_between_clear_and_redraw() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        let observed = bar.suspend(|| tw.output());
+        assert_eq!(observed, "\x1b[?25l\r0\r\x1b[2K\x1b[?25h");
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r\x1b[2K\x1b[?25h\x1b[?25l\r0");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::{Arc, Mutex};
+    #[test]
+    fn test_panic_in_suspend_leaves_bar_usable() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Never)
+            .template("{pos} {msg}")
+            .unwrap()
+            .start();
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| bar.suspend(|| panic!("boom"))));
+        assert!(caught.is_err());
+        bar.set_message("after");
+        bar.tick(2);
+        bar.success("done");
+        let out = tw.output();
+        assert!(out.contains("\r2 after"), "got: {out:?}");
+        assert!(out.ends_with("\r\x1b[2K✔ done\n"), "got: {out:?}");
+    }
 
-    // --- Task 4.1: Test helper ---
+    // --- Width ---
 
-    #[derive(Clone)]
-    struct TestWriter(Arc<Mutex<Vec<u8>>>);
+    #[test]
+    fn test_long_message_truncated_to_max_width() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .width(10)
+            .max_width(30)
+            .message("a very long message that would wrap")
+            .start();
+        bar.tick(5);
+        let out = tw.output();
+        let last = out.lines().last().unwrap();
+        assert_eq!(last, "[█████░░░░░]  50% 5/10 a very…");
+        assert_eq!(last.chars().count(), 30);
+    }
 
-    impl TestWriter {
-        fn new() -> Self {
-            TestWriter(Arc::new(Mutex::new(Vec::new())))
-        }
+    #[test]
+    fn test_final_message_truncated_to_max_width() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Never)
+            .max_width(20)
+            .start();
+        bar.success(&"x".repeat(60));
+        let out = tw.output();
+        let last = out.rsplit("\x1b[2K").next().unwrap();
+        assert_eq!(last, format!("✔ {}…\n", "x".repeat(17)));
+    }
 
-        fn output(&self) -> String {
-            String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
-        }
+    #[test]
+    fn test_auto_width_fills_remaining_space() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .auto_width(true)
+            .max_width(40)
+            .message("msg")
+            .start();
+        let out = tw.output();
+        let line = out.lines().next().unwrap();
+        assert_eq!(line.chars().count(), 40);
+        assert_eq!(line, format!("[{}]   0% 0/10 msg", "░".repeat(24)));
     }
 
-    impl io::Write for TestWriter {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            self.0.lock().unwrap().write(buf)
-        }
-        fn flush(&mut self) -> io::Result<()> {
-            Ok(())
-        }
+    #[test]
+    fn test_auto_width_keeps_minimum_and_truncates_message() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .auto_width(true)
+            .max_width(30)
+            .message("a message much too long to fit")
+            .start();
+        let out = tw.output();
+        let line = out.lines().next().unwrap();
+        assert_eq!(line, "[░░░░░░░░░░]   0% 0/10 a mess…");
     }
 
-    fn make_writer() -> (TestWriter, Box<dyn Write + Send>) {
-        let tw = TestWriter::new();
-        let boxed: Box<dyn Write + Send> = Box::new(tw.clone());
-        (tw, boxed)
+    #[test]
+    fn test_fixed_fields_wider_than_max_are_cut() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10).writer(w).max_width(8).start();
+        assert_eq!(tw.output(), "[░░░░░░…\n");
     }
 
-    // --- Task 4.2: Builder defaults and custom writer ---
+    #[test]
+    fn test_auto_width_without_known_width_uses_fixed_width() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .width(5)
+            .auto_width(true)
+            .start();
+        assert_eq!(tw.output(), "[░░░░░]   0% 0/10\n");
+    }
 
     #[test]
-    fn test_builder_defaults() {
+    fn test_wide_message_truncated_by_columns() {
         let (tw, w) = make_writer();
-        let _bar = ProgressBar::new(100).writer(w).start();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos} {msg}")
+            .unwrap()
+            .max_width(8)
+            .message("日本語のメッセージ")
+            .start();
         let out = tw.output();
-        // Default bar: 40 chars of empty char, 0%, 0/100
-        assert!(
-            out.contains("0% 0/100"),
-            "expected default render, got: {out}"
-        );
+        // "語" and "…" would need three columns; only two are left.
+        assert_eq!(out, "0 日本…\n");
+        assert_eq!(str_width(out.trim_end()), 7);
     }
 
     #[test]
-    fn test_custom_writer_receives_output() {
+    fn test_wide_fill_char_keeps_bar_width() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).start();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .width(9)
+            .fill('🟩')
+            .empty('-')
+            .template("{bar}|")
+            .unwrap()
+            .start();
         bar.tick(5);
         let out = tw.output();
-        assert!(!out.is_empty(), "custom writer should receive output");
-        assert!(out.contains("5/10"));
+        let last = out.lines().last().unwrap();
+        assert_eq!(last, "🟩🟩-----|");
+        assert_eq!(str_width(last), 10);
+
+        bar.tick(4);
+        bar.tick(1);
+        let out = tw.output();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[lines.len() - 2..], ["🟩🟩🟩🟩-|", "🟩🟩🟩🟩 |"]);
     }
 
     #[test]
-    fn test_start_produces_initial_render() {
+    fn test_auto_width_with_wide_message() {
         let (tw, w) = make_writer();
-        let _bar = ProgressBar::new(50).writer(w).start();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .auto_width(true)
+            .max_width(40)
+            .message("中文")
+            .start();
         let out = tw.output();
-        assert!(out.contains("0/50"), "start should render initial state");
+        assert_eq!(str_width(out.lines().next().unwrap()), 40);
     }
 
-    // --- Task 4.3: TTY vs non-TTY rendering ---
+    // --- Bar style ---
 
     #[test]
-    fn test_tty_mode_uses_cr() {
+    fn test_smooth_bar_draws_partial_cell() {
         let (tw, w) = make_writer();
-        let _bar = ProgressBar::new(10).writer(w).tty(true).start();
-        let out = tw.output();
-        assert!(
-            out.starts_with('\r'),
-            "TTY mode should start with \\r, got: {out}"
-        );
+        let bar = ProgressBar::new(32)
+            .writer(w)
+            .width(4)
+            .bar_style(BarStyle::Smooth)
+            .template("{bar}|")
+            .unwrap()
+            .start();
+        bar.tick(5);
+        bar.tick(8);
+        bar.tick(19);
+        assert_eq!(tw.output(), "░░░░|\n▋░░░|\n█▋░░|\n████|\n");
     }
 
     #[test]
-    fn test_non_tty_mode_uses_newlines_no_ansi() {
+    fn test_smooth_ascii_keeps_custom_fill_and_empty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(40)
+            .writer(w)
+            .width(4)
+            .fill('#')
+            .empty('-')
+            .bar_style(BarStyle::SmoothAscii)
+            .template("{bar}|")
+            .unwrap()
+            .start();
+        bar.tick(13);
+        assert!(tw.output().ends_with("#3--|\n"), "got: {}", tw.output());
+    }
+
+    #[test]
+    fn test_cells_style_rounds_to_whole_cells() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).start(); // custom writer defaults non-TTY
+        let bar = ProgressBar::new(32)
+            .writer(w)
+            .width(4)
+            .template("{bar}|")
+            .unwrap()
+            .start();
         bar.tick(5);
-        bar.success("done");
-        let out = tw.output();
-        assert!(!out.contains("\x1b["), "non-TTY should have no ANSI codes");
-        assert!(out.contains('\n'), "non-TTY should use newlines");
+        assert!(tw.output().ends_with("█░░░|\n"), "got: {}", tw.output());
     }
 
+    // --- Themes ---
+
     #[test]
-    fn test_custom_writer_defaults_non_tty() {
+    fn test_theme_styles_each_part_on_tty() {
         let (tw, w) = make_writer();
-        let _bar = ProgressBar::new(10).writer(w).start();
+        let theme = Theme::plain()
+            .filled(Style::new().fg(Color::Green))
+            .empty(Style::new().dim())
+            .percent(Style::new().bold())
+            .message(Style::new().fg(Color::Cyan).bg(Color::Black))
+            .success(Style::new().fg(Color::BrightGreen));
+        let bar = ProgressBar::new(4)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Always)
+            .width(4)
+            .theme(theme)
+            .message("go")
+            .template("{bar} {percent}% {msg}")
+            .unwrap()
+            .start();
+        bar.tick(1);
+        bar.success("ok");
         let out = tw.output();
-        // Non-TTY: no \r prefix, uses newline
         assert!(
-            !out.starts_with('\r'),
-            "custom writer should default to non-TTY"
+            out.contains(
+                "\r\x1b[32m█\x1b[0m\x1b[2m░░░\x1b[0m \x1b[1m25\x1b[0m% \x1b[36;40mgo\x1b[0m"
+            ),
+            "got: {out:?}"
         );
+        assert!(out.ends_with("\x1b[92m✔\x1b[0m ok\n"), "got: {out:?}");
     }
 
     #[test]
-    fn test_non_tty_finalization_omits_ansi() {
+    fn test_color_choice_overrides_detection() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).start();
-        bar.success("all good");
+        let bar = ProgressBar::new(4)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Never)
+            .start();
+        bar.success("ok");
+        assert!(!tw.output().contains("\x1b[3"), "got: {:?}", tw.output());
+
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(4)
+            .writer(w)
+            .color(ColorChoice::Always)
+            .start();
+        bar.fail("no");
+        assert!(tw.output().ends_with("\x1b[31m✖\x1b[0m no\n"));
+    }
+
+    #[test]
+    fn test_theme_ignored_when_piped() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(4)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .theme(Theme::vivid())
+            .start();
+        bar.tick(2);
+        bar.fail("no");
+        assert!(!tw.output().contains("\x1b["));
+    }
+
+    #[test]
+    fn test_styles_do_not_count_toward_width() {
+        let (tw, w) = make_writer();
+        let _bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .color(ColorChoice::Always)
+            .theme(Theme::vivid())
+            .auto_width(true)
+            .max_width(40)
+            .message("a message much too long to fit here")
+            .start();
         let out = tw.output();
-        assert!(
-            !out.contains("\x1b["),
-            "non-TTY finalization should omit ANSI"
-        );
-        assert!(out.contains("✔"));
-        assert!(out.contains("all good"));
+        assert_eq!(str_width(&out), 40, "got: {out:?}");
+        assert!(out.ends_with("…\x1b[0m"), "got: {out:?}");
     }
 
-    // --- Task 4.4: Drop behavior ---
+    // --- Cursor ---
 
     #[test]
-    fn test_drop_without_finalization_writes_newline() {
+    fn test_drop_shows_cursor_on_tty() {
         let (tw, w) = make_writer();
         {
-            let _bar = ProgressBar::new(10).writer(w).start();
-            // dropped here without success/fail
+            let _bar = ProgressBar::new(10)
+                .writer(w)
+                .tty(true)
+                .template("{pos}")
+                .unwrap()
+                .start();
         }
-        let out = tw.output();
-        assert!(out.ends_with('\n'), "drop should write trailing newline");
+        assert_eq!(tw.output(), "\x1b[?25l\r0\x1b[?25h\r\x1b[2K0\n");
     }
 
     #[test]
-    fn test_drop_after_finalization_no_extra_output() {
+    fn test_caught_panic_hides_cursor_again() {
         let (tw, w) = make_writer();
-        let out_before;
-        {
-            let bar = ProgressBar::new(10).writer(w).start();
+        let bar = ProgressBar::new(10)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .redraw_interval(Duration::ZERO)
+            .restore_cursor_on_exit(true)
+            .start_with(Output::Writer(w), Some(Stream::Stderr), true);
+        let epoch = term::cursor_epoch(Stream::Stderr);
+        let _ = panic::catch_unwind(|| panic!("caught"));
+        assert!(term::cursor_epoch(Stream::Stderr) > epoch);
+        bar.tick(1);
+        bar.tick(1);
+        assert_eq!(tw.output(), "\x1b[?25l\r0\x1b[?25l\r1\r2");
+    }
+
+    // --- Drop policy ---
+
+    #[test]
+    fn test_on_drop_policies() {
+        for (on_drop, expected) in [
+            (OnDrop::Leave, "\x1b[?25l\r0\r3\x1b[?25h"),
+            (OnDrop::Clear, "\x1b[?25l\r0\r3\x1b[?25h\r\x1b[2K"),
+            (
+                OnDrop::Fail("interrupted".into()),
+                "\x1b[?25l\r0\r3\x1b[?25h\r\x1b[2K✖ interrupted\n",
+            ),
+            (
+                OnDrop::Success("done".into()),
+                "\x1b[?25l\r0\r3\x1b[?25h\r\x1b[2K✔ done\n",
+            ),
+            (OnDrop::Abandon, "\x1b[?25l\r0\r3\x1b[?25h\r\x1b[2K3\n"),
+        ] {
+            let (tw, w) = make_writer();
+            let bar = ProgressBar::new(10)
+                .writer(w)
+                .tty(true)
+                .color(ColorChoice::Never)
+                .template("{pos}")
+                .unwrap()
+                .on_drop(on_drop.clone())
+                .start();
+            let clone = bar.clone();
+            bar.set_position(3);
+            drop(bar);
+            assert_eq!(tw.output(), "\x1b[?25l\r0\r3", "{on_drop:?} on a clone");
+            drop(clone);
+            assert_eq!(tw.output(), expected, "{on_drop:?}");
+        }
+    }
+
+    #[test]
+    fn test_on_drop_fail_after_early_return() {
+        fn work(bar: &ProgressBar) -> Result<(), String> {
+            bar.tick(1);
+            Err("disk full".to_string())?;
             bar.success("done");
-            out_before = tw.output();
+            Ok(())
         }
-        let out_after = tw.output();
-        assert_eq!(
-            out_before, out_after,
-            "drop after finalization should add nothing"
-        );
+        let (tw, w) = make_writer();
+        {
+            let bar = ProgressBar::new(10)
+                .writer(w)
+                .color(ColorChoice::Never)
+                .template("{pos}")
+                .unwrap()
+                .on_drop(OnDrop::Fail("failed".into()))
+                .start();
+            assert!(work(&bar).is_err());
+        }
+        assert_eq!(tw.output(), "0\n1\n✖ failed\n");
     }
 
-    // --- Task 4.5: Send + Sync assertion ---
+    // --- Finalizing from a Result ---
 
     #[test]
-    fn test_progress_bar_is_send_and_sync() {
-        fn assert_send_sync<T: Send + Sync>() {}
-        assert_send_sync::<ProgressBar>();
+    fn test_finish_with_result() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .message("copied")
+            .start();
+        bar.finish_with_result(&Ok::<_, String>(()));
+        assert_eq!(bar.snapshot().outcome, Outcome::Succeeded);
+
+        let (tw2, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        let err = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        bar.finish_with_result(&Err::<(), _>(err));
+        assert_eq!(bar.snapshot().outcome, Outcome::Failed);
+
+        assert_eq!(tw.output(), "0\n✔ copied\n");
+        assert_eq!(tw2.output(), "0\n✖ no such file\n");
     }
 
     #[test]
-    fn test_fill_and_empty_builder_methods() {
+    fn test_run_finalizes_from_result() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).fill('#').empty('-').start();
-        bar.tick(5);
-        let out = tw.output();
-        assert!(out.contains('#'), "custom fill char should appear");
-        assert!(out.contains('-'), "custom empty char should appear");
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .message("done")
+            .start();
+        let result = bar.run(|bar| {
+            bar.tick(2);
+            Ok::<_, String>(7)
+        });
+        assert_eq!(result, Ok(7));
+        assert_eq!(tw.output(), "0\n2\n✔ done\n");
+
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        let result: Result<(), String> = bar.run(|_| Err("disk full".to_string()));
+        assert!(result.is_err());
+        assert_eq!(tw.output(), "0\n✖ disk full\n");
+
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        let _ = bar.run(|bar| {
+            bar.warn("partial");
+            Ok::<_, String>(())
+        });
+        assert_eq!(tw.output(), "0\n⚠ partial\n");
     }
 
     #[test]
-    fn test_set_message_updates_output() {
+    fn test_run_fails_bar_on_panic() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).start();
-        bar.set_message("hello");
-        bar.tick(1);
-        let out = tw.output();
-        assert!(
-            out.contains("hello"),
-            "set_message should update displayed text"
-        );
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .color(ColorChoice::Never)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+            bar.run(|bar| -> Result<(), String> { bar.suspend(|| panic!("out of cheese")) })
+        }));
+        assert!(caught.is_err(), "the panic should carry on");
+        assert_eq!(bar.snapshot().outcome, Outcome::Failed);
+        assert_eq!(tw.output(), "0\n✖ out of cheese\n");
     }
 
+    // --- Concurrent ticks ---
+
     #[test]
-    fn test_tty_finalize_success_has_ansi() {
+    fn test_tick_during_lock_drawn_on_release() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).tty(true).start();
-        bar.success("completed");
-        let out = tw.output();
-        assert!(
-            out.contains("\x1b[32m"),
-            "TTY success should have green ANSI code"
-        );
-        assert!(out.contains("✔"), "TTY success should have checkmark");
-        assert!(out.contains("completed"));
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .redraw_interval(Duration::ZERO)
+            .start();
+        let held = bar.lock();
+        bar.tick(3);
+        assert_eq!(tw.output(), "\x1b[?25l\r0");
+        drop(held);
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r3");
     }
 
     #[test]
-    fn test_tty_finalize_fail_has_ansi() {
+    fn test_concurrent_ticks_drawn_by_finish() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).tty(true).start();
-        bar.fail("broken");
-        let out = tw.output();
-        assert!(
-            out.contains("\x1b[31m"),
-            "TTY fail should have red ANSI code"
-        );
-        assert!(out.contains("✖"), "TTY fail should have cross mark");
-        assert!(out.contains("broken"));
+        let bar = ProgressBar::new(8000)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .redraw_interval(Duration::from_secs(3600))
+            .start();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                let bar = bar.clone();
+                scope.spawn(move || (0..1000).for_each(|_| bar.tick(1)));
+            }
+        });
+        assert_eq!(bar.position(), 8000);
+        bar.finish();
+        assert_eq!(tw.output(), "\x1b[?25l\r0\r8000\x1b[?25h\r\x1b[2K8000\n");
     }
 
     #[test]
-    fn test_double_finalization_is_noop() {
+    fn test_tick_while_suspended_does_not_block() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(10).writer(w).tty(true).start();
-        bar.success("first");
-        let out_after_first = tw.output();
-        bar.fail("second");
-        let out_after_second = tw.output();
-        assert_eq!(
-            out_after_first, out_after_second,
-            "double finalization should be a no-op"
-        );
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .tty(true)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        bar.suspend(|| bar.tick(3));
+        assert_eq!(bar.position(), 3);
+        bar.tick(1);
+        assert!(tw.output().ends_with("\r4"), "got: {:?}", tw.output());
+    }
+
+    // --- Steady tick ---
+
+    /// Poll `done` until it holds, failing the test after five seconds.
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting");
+            std::thread::sleep(Duration::from_millis(1));
+        }
     }
 
     #[test]
-    fn test_total_zero_normalized_to_one() {
+    fn test_steady_tick_animates_until_finished() {
         let (tw, w) = make_writer();
-        let bar = ProgressBar::new(0).writer(w).start();
-        let s = bar.state.lock().unwrap();
-        assert_eq!(s.total, 1, "total of 0 should be normalized to 1");
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .tty(true)
+            .template("{spinner}")
+            .unwrap()
+            .frames(&["a", "b", "c"])
+            .start();
+        bar.enable_steady_tick(Duration::from_millis(5));
+        wait_until(|| tw.output().contains("\rb\rc\ra"));
+        bar.success("done");
+        let finished = tw.output();
+        // The thread holds the only weak reference, until it exits.
+        wait_until(|| Arc::weak_count(&bar.state) == 0);
+        assert_eq!(tw.output(), finished);
+        assert!(finished.ends_with("done\n"));
+    }
+
+    #[test]
+    fn test_steady_tick_stops_on_last_drop() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::spinner()
+            .writer(w)
+            .tty(true)
+            .template("{spinner}")
+            .unwrap()
+            .start();
+        bar.enable_steady_tick(Duration::from_millis(5));
+        let state = Arc::downgrade(&bar.state);
+        wait_until(|| tw.output().matches('\r').count() >= 3);
+        drop(bar);
+        let dropped = tw.output();
+        // The thread must not keep the bar alive, nor draw after the drop.
+        wait_until(|| state.upgrade().is_none());
+        assert_eq!(tw.output(), dropped);
+    }
+
+    #[test]
+    fn test_steady_tick_disable_and_non_tty() {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}")
+            .unwrap()
+            .redraw_interval(Duration::from_millis(20))
+            .start();
+        bar.enable_steady_tick(Duration::from_millis(5));
+        // Ticks skipped by the redraw interval are drawn by the thread, once.
+        bar.tick(1);
+        wait_until(|| tw.output().ends_with("1\n"));
+        bar.tick(1);
+        wait_until(|| tw.output().ends_with("2\n"));
+        assert_eq!(tw.output(), "0\n1\n2\n", "no new lines without progress");
+        bar.disable_steady_tick();
+        assert!(bar.state.lock().unwrap().ticker.is_none());
     }
 
     // --- Property tests using quickcheck! macro ---
@@ -553,7 +3366,7 @@ mod tests {
                 && s.config.fill == '█'
                 && s.config.empty == '░'
                 && s.current == 0
-                && s.total == total
+                && s.total == Some(total)
         }
 
         // --- Task 4.7: Property 2 - Render output correctness ---
@@ -562,7 +3375,7 @@ mod tests {
         fn prop_render_output(current: u64, total: u64, width: u8, msg_bytes: Vec<u8>) -> bool {
             let total = total.max(1);
             let current = current.min(total);
-            let width = (width as usize).max(1).min(200);
+            let width = (width as usize).clamp(1, 200);
             let msg: String = String::from_utf8_lossy(&msg_bytes)
                 .chars()
                 .filter(|c| !c.is_control())
@@ -610,9 +3423,8 @@ mod tests {
             for t in &ticks {
                 bar.tick(*t);
             }
-            let s = bar.state.lock().unwrap();
             let expected = ticks.iter().fold(0u64, |acc, t| acc.saturating_add(*t)).min(total);
-            s.current == expected
+            bar.position() == expected
         }
 
         // --- Task 4.9: Property 4 - Finalization output ---
@@ -650,10 +3462,10 @@ mod tests {
             let bar = ProgressBar::new(total).writer(w).start();
             bar.success("done");
             let out_before = tw.output();
-            let counter_before = bar.state.lock().unwrap().current;
+            let counter_before = bar.position();
             bar.tick(tick_amount.max(1));
             let out_after = tw.output();
-            let counter_after = bar.state.lock().unwrap().current;
+            let counter_after = bar.position();
             counter_before == counter_after && out_before == out_after
         }
 
@@ -669,7 +3481,11 @@ mod tests {
                 .collect();
 
             let (tw, w) = make_writer();
-            let bar = ProgressBar::new(total).writer(w).message(&msg).start();
+            let bar = ProgressBar::new(total)
+                .writer(w)
+                .color(ColorChoice::Never)
+                .message(&msg)
+                .start();
             for t in &ticks {
                 bar.tick(*t);
             }
@@ -699,9 +3515,68 @@ mod tests {
                 }
             });
 
-            let s = bar.state.lock().unwrap();
             let expected = tick_amounts.iter().fold(0u64, |acc, t| acc.saturating_add(*t)).min(total);
-            s.current == expected
+            bar.position() == expected
+        }
+
+        // Feature: nanoprogress, Property 8: Rendered lines never exceed the target width
+        fn prop_lines_fit_max_width(
+            max: u8,
+            msg: String,
+            prefix: String,
+            fill: char,
+            auto: bool,
+            smooth: bool,
+            ticks: Vec<u64>
+        ) -> bool {
+            let max = max as usize;
+            let clean = |s: String| -> String { s.chars().filter(|c| !c.is_control()).collect() };
+            let fill = if fill.is_control() { '#' } else { fill };
+
+            let (tw, w) = make_writer();
+            let bar = ProgressBar::new(1000)
+                .writer(w)
+                .max_width(max)
+                .auto_width(auto)
+                .fill(fill)
+                .bar_style(if smooth { BarStyle::Smooth } else { BarStyle::Cells })
+                .prefix(&clean(prefix))
+                .message(&clean(msg))
+                .template("{prefix} [{bar}] {percent:>3}% {pos}/{len} {msg}")
+                .unwrap()
+                .start();
+            for t in ticks {
+                bar.tick(t);
+            }
+            let out = tw.output();
+            out.lines().all(|line| str_width(line) <= max)
+        }
+
+        // Feature: nanoprogress, Property 9: Position and length updates keep 1 <= length and position <= length
+        fn prop_position_and_length_ops(total: u64, ops: Vec<(u8, u64)>) -> bool {
+            let (_, w) = make_writer();
+            let bar = ProgressBar::new(total).writer(w).start();
+            let (mut pos, mut len) = (0u64, total.max(1));
+            for (op, n) in ops {
+                match op % 6 {
+                    0 => bar.tick(n),
+                    1 => bar.dec(n),
+                    2 => bar.set_position(n),
+                    3 => bar.set_length(n),
+                    4 => bar.inc_length(n),
+                    _ => bar.reset(),
+                }
+                match op % 6 {
+                    0 => pos = pos.saturating_add(n),
+                    1 => pos = pos.saturating_sub(n),
+                    2 => pos = n,
+                    3 => len = n.max(1),
+                    4 => len = len.saturating_add(n),
+                    _ => pos = 0,
+                }
+                pos = pos.min(len);
+            }
+            bar.position() == pos && bar.length() == Some(len)
         }
     }
 }
@@ -0,0 +1,538 @@
+//! Several progress bars drawn together on one writer.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::term::{self, Stream};
+use crate::{DrawTarget, ProgressBar, ProgressBarBuilder};
+
+struct Line {
+    id: usize,
+    label: String,
+    text: String,
+}
+
+pub(crate) struct MultiState {
+    writer: Box<dyn Write + Send>,
+    is_tty: bool,
+    tty_override: Option<bool>,
+    stream: Option<Stream>,
+    /// Drawing to [`DrawTarget::Hidden`]: bars are started hidden and
+    /// nothing is printed.
+    hidden: bool,
+    lines: Vec<Line>,
+    next_id: usize,
+    /// Number of live lines currently on screen, i.e. how far up the cursor
+    /// has to move to redraw the live area.
+    drawn: usize,
+    /// Whether the cursor is hidden, which it is while there are live bars,
+    /// and the stream's [`cursor_epoch`](term::cursor_epoch) when it was.
+    cursor_hidden: bool,
+    cursor_epoch: usize,
+}
+
+impl MultiState {
+    fn position(&self, id: usize) -> Option<usize> {
+        self.lines.iter().position(|l| l.id == id)
+    }
+
+    /// Redraw the live area in place. `above` is printed once, permanently,
+    /// above the live bars.
+    fn redraw(&mut self, above: Option<&str>) {
+        let mut out = String::new();
+        if !self.lines.is_empty() {
+            self.set_cursor_hidden(&mut out, true);
+        }
+        if self.drawn > 0 {
+            write!(out, "\r\x1b[{}A", self.drawn).ok();
+        }
+        if let Some(text) = above {
+            writeln!(out, "\x1b[2K{text}").ok();
+        }
+        for line in &self.lines {
+            writeln!(out, "\x1b[2K{}", line.text).ok();
+        }
+        out.push_str("\x1b[J");
+        if self.lines.is_empty() {
+            self.set_cursor_hidden(&mut out, false);
+        }
+        self.drawn = self.lines.len();
+        self.writer.write_all(out.as_bytes()).ok();
+        self.writer.flush().ok();
+    }
+
+    /// Append the sequence hiding or showing the cursor to `out`, if it isn't
+    /// already in that state. The panic hook shows the cursor behind our
+    /// back, so it's hidden again if that happened since.
+    fn set_cursor_hidden(&mut self, out: &mut String, hidden: bool) {
+        let epoch = self.stream.map_or(0, term::cursor_epoch);
+        if self.cursor_hidden == hidden && (!hidden || self.cursor_epoch == epoch) {
+            return;
+        }
+        out.push_str(if hidden {
+            term::HIDE_CURSOR
+        } else {
+            term::SHOW_CURSOR
+        });
+        if let (Some(stream), true) = (self.stream, self.cursor_hidden != hidden) {
+            term::track_cursor(stream, hidden);
+        }
+        self.cursor_hidden = hidden;
+        self.cursor_epoch = epoch;
+    }
+
+    /// Erase the live area, leaving the cursor where it started.
+    fn clear(&mut self) {
+        if self.drawn > 0 {
+            write!(self.writer, "\r\x1b[{}A\x1b[J", self.drawn).ok();
+            self.writer.flush().ok();
+            self.drawn = 0;
+        }
+    }
+
+    fn println(&mut self, text: &str) {
+        if self.hidden {
+            return;
+        }
+        if self.is_tty {
+            self.redraw(Some(text));
+        } else {
+            writeln!(self.writer, "{text}").ok();
+            self.writer.flush().ok();
+        }
+    }
+
+    fn suspend<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        if self.hidden || !self.is_tty {
+            return f();
+        }
+        self.clear();
+        let mut out = String::new();
+        self.set_cursor_hidden(&mut out, false);
+        self.writer.write_all(out.as_bytes()).ok();
+        self.writer.flush().ok();
+        let result = f();
+        self.redraw(None);
+        result
+    }
+
+    fn log(&mut self, label: &str, text: &str) {
+        writeln!(self.writer, "[{label}] {text}").ok();
+        self.writer.flush().ok();
+    }
+}
+
+/// Lock the container. A closure passed to
+/// [`suspend`](MultiProgress::suspend) that panics poisons the lock, but the
+/// lines are never left half-updated, so drawing carries on.
+fn lock(multi: &Mutex<MultiState>) -> MutexGuard<'_, MultiState> {
+    multi.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A child bar's place in a [`MultiProgress`].
+pub(crate) struct Slot {
+    multi: Arc<Mutex<MultiState>>,
+    id: usize,
+}
+
+impl Slot {
+    pub(crate) fn is_tty(&self) -> bool {
+        lock(&self.multi).is_tty
+    }
+
+    /// Column count of the terminal the container draws to, if known.
+    pub(crate) fn term_width(&self) -> Option<usize> {
+        let stream = lock(&self.multi).stream;
+        stream.and_then(term::width)
+    }
+
+    /// This slot's id, if it belongs to `multi`.
+    pub(crate) fn id_in(&self, multi: &Arc<Mutex<MultiState>>) -> Option<usize> {
+        Arc::ptr_eq(&self.multi, multi).then_some(self.id)
+    }
+
+    /// Replace this bar's line. Lines of bars that were removed are dropped.
+    pub(crate) fn draw(&self, prefix: &str, text: &str) {
+        let mut m = lock(&self.multi);
+        let Some(i) = m.position(self.id) else {
+            return;
+        };
+        let line = &mut m.lines[i];
+        line.text.clear();
+        line.text.push_str(text);
+        if !prefix.is_empty() && line.label != prefix {
+            line.label = prefix.to_string();
+        }
+        if m.is_tty {
+            m.redraw(None);
+        } else {
+            let label = m.lines[i].label.clone();
+            m.log(&label, text);
+        }
+    }
+
+    pub(crate) fn println(&self, text: &str) {
+        lock(&self.multi).println(text);
+    }
+
+    pub(crate) fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        lock(&self.multi).suspend(f)
+    }
+
+    /// Take this bar out of the live area. With `Some(text)`, the text is
+    /// printed above the live area; with `None` the bar's line just disappears.
+    pub(crate) fn finish(&self, text: Option<&str>) {
+        let mut m = lock(&self.multi);
+        let Some(i) = m.position(self.id) else {
+            return;
+        };
+        let line = m.lines.remove(i);
+        if m.is_tty {
+            m.redraw(text);
+        } else if let Some(text) = text {
+            m.log(&line.label, text);
+        }
+    }
+}
+
+/// A container that draws several [`ProgressBar`]s on separate lines of one
+/// writer.
+///
+/// Bars are added with [`add`](MultiProgress::add) and may be added or
+/// removed while others are running. In TTY mode the live bars are redrawn in
+/// place with cursor-up and clear-line sequences. A finished bar moves above
+/// the live area with its final symbol and message, or disappears if it was
+/// built with [`clear_on_finish`](ProgressBarBuilder::clear_on_finish).
+///
+/// When the output is not a terminal, each update is written on its own line,
+/// prefixed with the bar's prefix (or its number if it has none), so
+/// interleaved output stays readable in logs.
+///
+/// ```no_run
+/// use nanoprogress::{MultiProgress, ProgressBar};
+/// use std::thread;
+///
+/// let multi = MultiProgress::new();
+/// let handles: Vec<_> = (1..=3)
+///     .map(|i| {
+///         let bar = multi.add(ProgressBar::new(100).prefix(&format!("job {i}")));
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 bar.tick(1);
+///             }
+///             bar.success("done");
+///         })
+///     })
+///     .collect();
+/// for h in handles {
+///     h.join().unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MultiProgress {
+    state: Arc<Mutex<MultiState>>,
+}
+
+impl MultiProgress {
+    /// Create an empty container drawing to stdout.
+    pub fn new() -> Self {
+        MultiProgress {
+            state: Arc::new(Mutex::new(MultiState {
+                writer: Box::new(io::stdout()),
+                is_tty: term::can_redraw(Stream::Stdout),
+                tty_override: None,
+                stream: Some(Stream::Stdout),
+                hidden: false,
+                lines: Vec::new(),
+                next_id: 0,
+                drawn: 0,
+                cursor_hidden: false,
+                cursor_epoch: 0,
+            })),
+        }
+    }
+
+    /// Direct output to a custom writer instead of stdout. Configure this
+    /// before adding bars.
+    /// Custom writers default to non-TTY mode unless overridden with [`.tty(true)`](MultiProgress::tty).
+    pub fn writer(self, writer: Box<dyn Write + Send>) -> Self {
+        self.draw_target(DrawTarget::Writer(writer))
+    }
+
+    /// Choose where the bars draw. Default: [`DrawTarget::Stdout`]. Configure
+    /// this before adding bars. With [`DrawTarget::Hidden`], bars are started
+    /// like [`ProgressBar::hidden`] ones and nothing is printed.
+    pub fn draw_target(self, target: DrawTarget) -> Self {
+        {
+            let mut m = lock(&self.state);
+            m.hidden = matches!(target, DrawTarget::Hidden);
+            let (writer, stream) = target.into_parts();
+            m.writer = writer;
+            m.is_tty = m
+                .tty_override
+                .unwrap_or_else(|| stream.is_some_and(term::can_redraw));
+            m.stream = stream;
+        }
+        self
+    }
+
+    /// Explicitly set TTY mode, overriding auto-detection. Configure this
+    /// before adding bars.
+    pub fn tty(self, is_tty: bool) -> Self {
+        {
+            let mut m = lock(&self.state);
+            m.is_tty = is_tty;
+            m.tty_override = Some(is_tty);
+        }
+        self
+    }
+
+    /// Start `builder` as a new bar at the bottom of the live area.
+    ///
+    /// The builder's own writer and TTY settings are ignored; the bar draws
+    /// through this container.
+    pub fn add(&self, builder: ProgressBarBuilder) -> ProgressBar {
+        let id = {
+            let mut m = lock(&self.state);
+            if m.hidden {
+                return builder.start_hidden();
+            }
+            let id = m.next_id;
+            m.next_id += 1;
+            m.lines.push(Line {
+                id,
+                label: (id + 1).to_string(),
+                text: String::new(),
+            });
+            id
+        };
+        builder.start_in(Slot {
+            multi: Arc::clone(&self.state),
+            id,
+        })
+    }
+
+    /// Print a line above the live area without corrupting it.
+    pub fn println(&self, text: &str) {
+        lock(&self.state).println(text);
+    }
+
+    /// Erase the live area, run `f`, and redraw the bars underneath whatever
+    /// `f` printed. Bars can't redraw while `f` runs, so `f` must not update
+    /// bars of this container.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        lock(&self.state).suspend(f)
+    }
+
+    /// Remove `bar` from the live area without printing anything for it.
+    /// Further updates to the bar are not drawn. Bars that don't belong to
+    /// this container are ignored.
+    pub fn remove(&self, bar: &ProgressBar) {
+        let Some(id) = bar.slot_id(&self.state) else {
+            return;
+        };
+        let mut m = lock(&self.state);
+        if let Some(i) = m.position(id) {
+            m.lines.remove(i);
+            if m.is_tty {
+                m.redraw(None);
+            }
+        }
+    }
+}
+
+impl Default for MultiProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_writer;
+    use crate::{ColorChoice, Output};
+
+    #[test]
+    fn test_bars_get_separate_lines() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        let b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        a.tick(3);
+        b.tick(4);
+        let out = tw.output();
+        assert!(
+            out.ends_with("\r\x1b[2A\x1b[2Ka 3\n\x1b[2Kb 4\n\x1b[J"),
+            "live area should be redrawn in place, got: {out:?}"
+        );
+    }
+
+    #[test]
+    fn test_finished_bar_moves_above_live_area() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(
+            ProgressBar::new(10)
+                .color(ColorChoice::Always)
+                .template("a {pos}")
+                .unwrap(),
+        );
+        let _b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        a.success("a done");
+        let out = tw.output();
+        assert!(
+            out.ends_with("\r\x1b[2A\x1b[2K\x1b[32m✔\x1b[0m a done\n\x1b[2Kb 0\n\x1b[J"),
+            "got: {out:?}"
+        );
+    }
+
+    #[test]
+    fn test_clear_on_finish_removes_line() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(
+            ProgressBar::new(10)
+                .template("a {pos}")
+                .unwrap()
+                .clear_on_finish(true),
+        );
+        let _b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        a.success("a done");
+        let out = tw.output();
+        assert!(!out.contains("a done"));
+        assert!(out.ends_with("\r\x1b[2A\x1b[2Kb 0\n\x1b[J"), "got: {out:?}");
+    }
+
+    #[test]
+    fn test_remove_while_running() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        let b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        multi.remove(&a);
+        let before = tw.output();
+        assert!(
+            before.ends_with("\r\x1b[2A\x1b[2Kb 0\n\x1b[J"),
+            "got: {before:?}"
+        );
+        a.tick(5);
+        assert_eq!(before, tw.output(), "removed bars should not draw");
+        b.tick(1);
+        assert!(tw.output().ends_with("\r\x1b[1A\x1b[2Kb 1\n\x1b[J"));
+    }
+
+    #[test]
+    fn test_non_tty_prefixed_lines() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(false);
+        let a = multi.add(
+            ProgressBar::new(10)
+                .prefix("fetch")
+                .color(ColorChoice::Never)
+                .template("{pos}")
+                .unwrap(),
+        );
+        let b = multi.add(
+            ProgressBar::new(10)
+                .color(ColorChoice::Never)
+                .template("{pos}")
+                .unwrap(),
+        );
+        b.tick(2);
+        a.tick(1);
+        a.success("ok");
+        assert_eq!(
+            tw.output(),
+            "[fetch] 0\n[2] 0\n[2] 2\n[fetch] 1\n[fetch] ✔ ok\n"
+        );
+    }
+
+    #[test]
+    fn test_println_above_live_area() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        multi.println("from multi");
+        a.println("from bar");
+        let out = tw.output();
+        assert!(
+            out.ends_with(
+                "\r\x1b[1A\x1b[2Kfrom multi\n\x1b[2Ka 0\n\x1b[J\
+                 \r\x1b[1A\x1b[2Kfrom bar\n\x1b[2Ka 0\n\x1b[J"
+            ),
+            "got: {out:?}"
+        );
+    }
+
+    #[test]
+    fn test_cursor_hidden_while_bars_are_live() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        let b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        a.success("a done");
+        let out = tw.output();
+        assert!(out.starts_with("\x1b[?25l"), "got: {out:?}");
+        assert_eq!(out.matches("\x1b[?25l").count(), 1);
+        assert!(!out.contains("\x1b[?25h"));
+        b.success("b done");
+        assert!(tw.output().ends_with("\x1b[J\x1b[?25h"));
+    }
+
+    #[test]
+    fn test_suspend_clears_and_redraws() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let _a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        let _b = multi.add(ProgressBar::new(10).template("b {pos}").unwrap());
+        let before = tw.output().len();
+        let got = multi.suspend(|| 42);
+        assert_eq!(got, 42);
+        assert_eq!(
+            &tw.output()[before..],
+            "\r\x1b[2A\x1b[J\x1b[?25h\x1b[?25l\x1b[2Ka 0\n\x1b[2Kb 0\n\x1b[J"
+        );
+    }
+
+    #[test]
+    fn test_panic_in_suspend_leaves_bars_usable() {
+        let (tw, w) = make_writer();
+        let multi = MultiProgress::new().writer(w).tty(true);
+        let a = multi.add(ProgressBar::new(10).template("a {pos}").unwrap());
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            multi.suspend(|| panic!("boom"))
+        }));
+        assert!(caught.is_err());
+        a.tick(1);
+        multi.println("still here");
+        assert!(
+            tw.output()
+                .ends_with("\x1b[2Kstill here\n\x1b[2Ka 1\n\x1b[J"),
+            "got: {:?}",
+            tw.output()
+        );
+    }
+
+    #[test]
+    fn test_hidden_target_starts_hidden_bars() {
+        let multi = MultiProgress::new().draw_target(DrawTarget::Hidden);
+        let (tw, w) = make_writer();
+        let a = multi.add(ProgressBar::new(10).writer(w).tty(true));
+        a.tick(3);
+        multi.println("quiet");
+        assert_eq!(multi.suspend(|| 7), 7);
+        assert!(matches!(a.state.lock().unwrap().output, Output::Hidden));
+        assert!(lock(&multi.state).lines.is_empty());
+        a.success("done");
+        assert_eq!(a.position(), 3);
+        assert_eq!(tw.output(), "");
+    }
+
+    #[test]
+    fn test_multi_progress_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<MultiProgress>();
+    }
+}
@@ -0,0 +1,476 @@
+//! Colors and text attributes for the parts of a rendered line.
+
+/// A terminal color.
+///
+/// The sixteen named colors work everywhere; [`Ansi256`](Color::Ansi256) and
+/// [`Rgb`](Color::Rgb) need a terminal with 256-color or truecolor support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// An entry of the 256-color palette.
+    Ansi256(u8),
+    /// A 24-bit truecolor value.
+    Rgb(u8, u8, u8),
+}
+
+/// The sixteen named colors, in palette order.
+const NAMED: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// Typical RGB values of the named colors (xterm's defaults).
+const NAMED_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Whether to use colors, set with
+/// [`ProgressBarBuilder::color`](crate::ProgressBarBuilder::color).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Use colors on terminals, following the `NO_COLOR`, `CLICOLOR`,
+    /// `CLICOLOR_FORCE` and `TERM` conventions.
+    #[default]
+    Auto,
+    /// Always use colors, even when the output is piped.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+/// How many colors an output supports. Styles using more are mapped to the
+/// nearest color that is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ColorLevel {
+    None,
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl Color {
+    /// The nearest color available at `level`.
+    fn downgrade(self, level: ColorLevel) -> Color {
+        match (self, level) {
+            (Color::Rgb(r, g, b), ColorLevel::Ansi256) => Color::Ansi256(rgb_to_ansi256(r, g, b)),
+            (Color::Rgb(r, g, b), ColorLevel::Basic) => nearest_named((r, g, b)),
+            (Color::Ansi256(n), ColorLevel::Basic) => nearest_named(ansi256_to_rgb(n)),
+            (color, _) => color,
+        }
+    }
+
+    /// SGR parameters selecting this color. `base` is 30 for the foreground
+    /// and 40 for the background.
+    fn sgr(self, base: u8) -> String {
+        let index = match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::BrightBlack => 8,
+            Color::BrightRed => 9,
+            Color::BrightGreen => 10,
+            Color::BrightYellow => 11,
+            Color::BrightBlue => 12,
+            Color::BrightMagenta => 13,
+            Color::BrightCyan => 14,
+            Color::BrightWhite => 15,
+            Color::Ansi256(n) => return format!("{};5;{n}", base + 8),
+            Color::Rgb(r, g, b) => return format!("{};2;{r};{g};{b}", base + 8),
+        };
+        match index {
+            0..=7 => (base + index).to_string(),
+            _ => (base + 60 + index - 8).to_string(),
+        }
+    }
+}
+
+/// Foreground and background colors plus bold and dim attributes.
+///
+/// Built by chaining from [`Style::new`], which applies no styling:
+///
+/// ```
+/// use nanoprogress::{Color, Style};
+///
+/// let style = Style::new().fg(Color::Rgb(255, 128, 0)).bold();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dim: bool,
+}
+
+impl Style {
+    /// A style that leaves text unchanged.
+    pub const fn new() -> Self {
+        Style {
+            fg: None,
+            bg: None,
+            bold: false,
+            dim: false,
+        }
+    }
+
+    /// Set the foreground color.
+    pub const fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Set the background color.
+    pub const fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    /// Draw text in bold.
+    pub const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Draw text dimmed.
+    pub const fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    /// Wrap `text` in the escape codes for this style, limited to the colors
+    /// of `level`. Plain styles, empty text and [`ColorLevel::None`] leave the
+    /// text unchanged.
+    pub(crate) fn paint(&self, text: &str, level: ColorLevel) -> String {
+        if text.is_empty() || *self == Style::new() || level == ColorLevel::None {
+            return text.to_string();
+        }
+        let mut params = Vec::new();
+        if self.bold {
+            params.push("1".to_string());
+        }
+        if self.dim {
+            params.push("2".to_string());
+        }
+        params.extend(self.fg.map(|fg| fg.downgrade(level).sgr(30)));
+        params.extend(self.bg.map(|bg| bg.downgrade(level).sgr(40)));
+        let sgr = params.join(";");
+        format!("\x1b[{sgr}m{text}\x1b[0m")
+    }
+}
+
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => NAMED_RGB[n as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// Nearest entry of the 6×6×6 color cube or the grayscale ramp.
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let step = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40,
+    };
+    let cube = 16 + 36 * step(r) + 6 * step(g) + step(b);
+    let mean = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray = match mean {
+        0..=7 => 232,
+        238.. => 255,
+        _ => 232 + ((mean - 8) / 10) as u8,
+    };
+    let target = (r, g, b);
+    if distance(ansi256_to_rgb(gray), target) < distance(ansi256_to_rgb(cube), target) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn nearest_named(rgb: (u8, u8, u8)) -> Color {
+    let index = (0..NAMED.len())
+        .min_by_key(|&i| distance(NAMED_RGB[i], rgb))
+        .unwrap_or_default();
+    NAMED[index]
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Styles for each part of a bar, set with
+/// [`ProgressBarBuilder::theme`](crate::ProgressBarBuilder::theme).
+///
+/// Styles are only applied when the output supports colors (see
+/// [`ColorChoice`]); 256-color and truecolor values are mapped to the nearest
+/// color the terminal supports. The default theme colors the symbols (green
+/// `✔`, red `✖`, yellow `⚠`, blue `ℹ`, dimmed `↓`) and leaves everything else
+/// unstyled.
+///
+/// ```no_run
+/// use nanoprogress::{Color, ProgressBar, Style, Theme};
+///
+/// let theme = Theme::default()
+///     .filled(Style::new().fg(Color::Ansi256(208)))
+///     .percent(Style::new().bold());
+/// let bar = ProgressBar::new(100).theme(theme).start();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub(crate) filled: Style,
+    pub(crate) empty: Style,
+    pub(crate) percent: Style,
+    pub(crate) message: Style,
+    pub(crate) success: Style,
+    pub(crate) fail: Style,
+    pub(crate) warning: Style,
+    pub(crate) info: Style,
+    pub(crate) skip: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            success: Style::new().fg(Color::Green),
+            fail: Style::new().fg(Color::Red),
+            warning: Style::new().fg(Color::Yellow),
+            info: Style::new().fg(Color::Blue),
+            skip: Style::new().dim(),
+            ..Theme::plain()
+        }
+    }
+}
+
+impl Theme {
+    /// No styling at all, not even on the final symbols.
+    pub const fn plain() -> Self {
+        Theme {
+            filled: Style::new(),
+            empty: Style::new(),
+            percent: Style::new(),
+            message: Style::new(),
+            success: Style::new(),
+            fail: Style::new(),
+            warning: Style::new(),
+            info: Style::new(),
+            skip: Style::new(),
+        }
+    }
+
+    /// Bright colors: a cyan bar on a dimmed track, bold percentage.
+    pub fn vivid() -> Self {
+        Theme {
+            filled: Style::new().fg(Color::BrightCyan),
+            empty: Style::new().fg(Color::BrightBlack),
+            percent: Style::new().bold(),
+            message: Style::new().fg(Color::BrightWhite),
+            success: Style::new().fg(Color::BrightGreen).bold(),
+            fail: Style::new().fg(Color::BrightRed).bold(),
+            warning: Style::new().fg(Color::BrightYellow).bold(),
+            info: Style::new().fg(Color::BrightBlue).bold(),
+            skip: Style::new().fg(Color::BrightBlack),
+        }
+    }
+
+    /// Attributes only, for terminals where colors are unwelcome.
+    pub fn mono() -> Self {
+        Theme {
+            filled: Style::new().bold(),
+            empty: Style::new().dim(),
+            percent: Style::new().bold(),
+            message: Style::new(),
+            success: Style::new().bold(),
+            fail: Style::new().bold(),
+            warning: Style::new().bold(),
+            info: Style::new().bold(),
+            skip: Style::new().dim(),
+        }
+    }
+
+    /// Style of the completed part of the bar.
+    pub fn filled(mut self, style: Style) -> Self {
+        self.filled = style;
+        self
+    }
+
+    /// Style of the remaining part of the bar.
+    pub fn empty(mut self, style: Style) -> Self {
+        self.empty = style;
+        self
+    }
+
+    /// Style of `{percent}`.
+    pub fn percent(mut self, style: Style) -> Self {
+        self.percent = style;
+        self
+    }
+
+    /// Style of `{msg}`.
+    pub fn message(mut self, style: Style) -> Self {
+        self.message = style;
+        self
+    }
+
+    /// Style of the `✔` printed by [`success`](crate::ProgressBar::success).
+    pub fn success(mut self, style: Style) -> Self {
+        self.success = style;
+        self
+    }
+
+    /// Style of the `✖` printed by [`fail`](crate::ProgressBar::fail).
+    pub fn fail(mut self, style: Style) -> Self {
+        self.fail = style;
+        self
+    }
+
+    /// Style of the `⚠` printed by [`warn`](crate::ProgressBar::warn).
+    pub fn warning(mut self, style: Style) -> Self {
+        self.warning = style;
+        self
+    }
+
+    /// Style of the `ℹ` printed by [`info`](crate::ProgressBar::info).
+    pub fn info(mut self, style: Style) -> Self {
+        self.info = style;
+        self
+    }
+
+    /// Style of the `↓` printed by [`skip`](crate::ProgressBar::skip).
+    pub fn skip(mut self, style: Style) -> Self {
+        self.skip = style;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_style_leaves_text_alone() {
+        assert_eq!(Style::new().paint("abc", ColorLevel::TrueColor), "abc");
+        assert_eq!(
+            Style::new().fg(Color::Red).paint("", ColorLevel::TrueColor),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_named_colors() {
+        assert_eq!(
+            Style::new()
+                .fg(Color::Green)
+                .paint("x", ColorLevel::TrueColor),
+            "\x1b[32mx\x1b[0m"
+        );
+        assert_eq!(
+            Style::new()
+                .fg(Color::BrightWhite)
+                .bg(Color::Blue)
+                .paint("x", ColorLevel::TrueColor),
+            "\x1b[97;44mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_extended_colors_and_attributes() {
+        assert_eq!(
+            Style::new()
+                .fg(Color::Ansi256(208))
+                .bold()
+                .paint("x", ColorLevel::TrueColor),
+            "\x1b[1;38;5;208mx\x1b[0m"
+        );
+        assert_eq!(
+            Style::new()
+                .bg(Color::Rgb(1, 2, 3))
+                .dim()
+                .paint("x", ColorLevel::TrueColor),
+            "\x1b[2;48;2;1;2;3mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_no_color_level_leaves_text_alone() {
+        let style = Style::new().fg(Color::Red).bold();
+        assert_eq!(style.paint("x", ColorLevel::None), "x");
+    }
+
+    #[test]
+    fn test_colors_downgrade_to_terminal_support() {
+        let orange = Style::new().fg(Color::Rgb(255, 135, 0));
+        assert_eq!(
+            orange.paint("x", ColorLevel::Ansi256),
+            "\x1b[38;5;208mx\x1b[0m"
+        );
+        assert_eq!(orange.paint("x", ColorLevel::Basic), "\x1b[33mx\x1b[0m");
+        let gray = Style::new().bg(Color::Rgb(128, 128, 128));
+        assert_eq!(
+            gray.paint("x", ColorLevel::Ansi256),
+            "\x1b[48;5;244mx\x1b[0m"
+        );
+        let green = Style::new().fg(Color::Ansi256(46));
+        assert_eq!(green.paint("x", ColorLevel::Basic), "\x1b[92mx\x1b[0m");
+        assert_eq!(
+            green.paint("x", ColorLevel::Ansi256),
+            "\x1b[38;5;46mx\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Line templates for [`ProgressBar`](crate::ProgressBar) rendering.
+//!
+//! A template is plain text with `{placeholder}` fields, optionally followed by
+//! a format spec: `{pos:>6}` right-aligns the position in six columns. Literal
+//! braces are written as `{{` and `}}`.
+
+use std::fmt;
+
+use crate::width::str_width;
+
+/// Error returned by [`ProgressBarBuilder::template`](crate::ProgressBarBuilder::template)
+/// when a template string cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `{name}` placeholder that is not a known field.
+    UnknownPlaceholder(String),
+    /// A `{` without a matching `}`.
+    Unclosed,
+    /// A `}` that does not close a placeholder. Use `}}` for a literal brace.
+    UnexpectedBrace,
+    /// A format spec after `:` that is not `[<>^][width]`.
+    InvalidSpec(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownPlaceholder(name) => {
+                write!(f, "unknown template placeholder `{{{name}}}`")
+            }
+            TemplateError::Unclosed => write!(f, "unclosed `{{` in template"),
+            TemplateError::UnexpectedBrace => {
+                write!(
+                    f,
+                    "unexpected `}}` in template (use `}}}}` for a literal brace)"
+                )
+            }
+            TemplateError::InvalidSpec(spec) => write!(f, "invalid format spec `{spec}`"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    Prefix,
+    Bar,
+    Pos,
+    Len,
+    Percent,
+    Msg,
+    Elapsed,
+    Eta,
+    PerSec,
+    Spinner,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name {
+            "prefix" => Field::Prefix,
+            "bar" => Field::Bar,
+            "pos" => Field::Pos,
+            "len" => Field::Len,
+            "percent" => Field::Percent,
+            "msg" => Field::Msg,
+            "elapsed" => Field::Elapsed,
+            "eta" => Field::Eta,
+            "per_sec" => Field::PerSec,
+            "spinner" => Field::Spinner,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Spec {
+    align: Align,
+    width: usize,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Spec {
+            align: Align::Left,
+            width: 0,
+        }
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec, TemplateError> {
+        let invalid = || TemplateError::InvalidSpec(spec.to_string());
+        let (align, digits) = match spec.chars().next() {
+            Some('<') => (Align::Left, &spec[1..]),
+            Some('>') => (Align::Right, &spec[1..]),
+            Some('^') => (Align::Center, &spec[1..]),
+            Some(_) => (Align::Left, spec),
+            None => return Err(invalid()),
+        };
+        let width = if digits.is_empty() {
+            0
+        } else {
+            digits.parse().map_err(|_| invalid())?
+        };
+        Ok(Spec { align, width })
+    }
+
+    /// Pad `s` to the spec's width in columns. Values already wider are left
+    /// untouched.
+    pub(crate) fn apply(&self, s: String) -> String {
+        let len = str_width(&s);
+        if len >= self.width {
+            return s;
+        }
+        let pad = self.width - len;
+        let (left, right) = match self.align {
+            Align::Left => (0, pad),
+            Align::Right => (pad, 0),
+            Align::Center => (pad / 2, pad - pad / 2),
+        };
+        let mut out = String::with_capacity(s.len() + pad);
+        out.extend(std::iter::repeat_n(' ', left));
+        out.push_str(&s);
+        out.extend(std::iter::repeat_n(' ', right));
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Literal(String),
+    Field(Field, Spec),
+}
+
+/// A parsed template, ready to be rendered on every redraw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Template {
+    pub(crate) pieces: Vec<Piece>,
+}
+
+impl Template {
+    pub(crate) const DEFAULT: &'static str = "[{bar}] {percent:>3}% {pos}/{len} {msg}";
+    pub(crate) const SPINNER: &'static str = "{spinner} {pos} {msg}";
+
+    pub(crate) fn parse(src: &str) -> Result<Template, TemplateError> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = src.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(TemplateError::UnexpectedBrace),
+                '{' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => return Err(TemplateError::Unclosed),
+                            Some(c) => inner.push(c),
+                        }
+                    }
+                    let (name, spec) = match inner.split_once(':') {
+                        Some((name, spec)) => (name, Spec::parse(spec)?),
+                        None => (inner.as_str(), Spec::default()),
+                    };
+                    let field = Field::from_name(name)
+                        .ok_or_else(|| TemplateError::UnknownPlaceholder(name.to_string()))?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field, spec));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template { pieces })
+    }
+}
+
+impl Template {
+    pub(crate) fn spinner() -> Self {
+        Template::parse(Template::SPINNER).expect("spinner template is valid")
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(Template::DEFAULT).expect("default template is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields_and_literals() {
+        let t = Template::parse("{prefix} [{bar}] {pos}/{len}").unwrap();
+        assert_eq!(
+            t.pieces,
+            vec![
+                Piece::Field(Field::Prefix, Spec::default()),
+                Piece::Literal(" [".into()),
+                Piece::Field(Field::Bar, Spec::default()),
+                Piece::Literal("] ".into()),
+                Piece::Field(Field::Pos, Spec::default()),
+                Piece::Literal("/".into()),
+                Piece::Field(Field::Len, Spec::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_escaped_braces() {
+        let t = Template::parse("{{{msg}}}").unwrap();
+        assert_eq!(
+            t.pieces,
+            vec![
+                Piece::Literal("{".into()),
+                Piece::Field(Field::Msg, Spec::default()),
+                Piece::Literal("}".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Template::parse("{nope}"),
+            Err(TemplateError::UnknownPlaceholder("nope".into()))
+        );
+        assert_eq!(Template::parse("{bar"), Err(TemplateError::Unclosed));
+        assert_eq!(Template::parse("bar}"), Err(TemplateError::UnexpectedBrace));
+        assert_eq!(
+            Template::parse("{pos:>x}"),
+            Err(TemplateError::InvalidSpec(">x".into()))
+        );
+    }
+
+    #[test]
+    fn test_spec_padding() {
+        let right = Spec::parse(">5").unwrap();
+        let center = Spec::parse("^5").unwrap();
+        let left = Spec::parse("5").unwrap();
+        assert_eq!(right.apply("ab".into()), "   ab");
+        assert_eq!(center.apply("ab".into()), " ab  ");
+        assert_eq!(left.apply("ab".into()), "ab   ");
+        assert_eq!(right.apply("abcdef".into()), "abcdef");
+        assert_eq!(right.apply("日本".into()), " 日本");
+    }
+}
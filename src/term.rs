@@ -0,0 +1,418 @@
+//! Terminal queries: TTY detection, terminal size and color support, plus
+//! cursor restoration on panic and Ctrl-C.
+
+use crate::style::ColorLevel;
+#[cfg(not(unix))]
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+
+/// A standard output stream that may be attached to a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[cfg(unix)]
+impl Stream {
+    fn fd(self) -> std::os::raw::c_int {
+        match self {
+            Stream::Stdout => 1,
+            Stream::Stderr => 2,
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Stream {
+    fn handle(self) -> *mut std::ffi::c_void {
+        use std::os::windows::io::AsRawHandle;
+        match self {
+            Stream::Stdout => io::stdout().as_raw_handle() as *mut _,
+            Stream::Stderr => io::stderr().as_raw_handle() as *mut _,
+        }
+    }
+}
+
+// --- TTY Detection ---
+
+#[cfg(unix)]
+pub(crate) fn is_tty(stream: Stream) -> bool {
+    extern "C" {
+        fn isatty(fd: std::os::raw::c_int) -> std::os::raw::c_int;
+    }
+    unsafe { isatty(stream.fd()) != 0 }
+}
+
+#[cfg(windows)]
+pub(crate) fn is_tty(stream: Stream) -> bool {
+    extern "system" {
+        fn GetConsoleMode(handle: *mut std::ffi::c_void, mode: *mut u32) -> i32;
+    }
+    let mut mode: u32 = 0;
+    unsafe { GetConsoleMode(stream.handle(), &mut mode) != 0 }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn is_tty(_stream: Stream) -> bool {
+    false
+}
+
+/// Whether `stream` is a terminal that can redraw a line in place. `TERM=dumb`
+/// terminals can't handle `\r` and escape codes, so they get line-by-line
+/// output like a pipe.
+pub(crate) fn can_redraw(stream: Stream) -> bool {
+    is_tty(stream) && !is_dumb(std::env::var("TERM").ok().as_deref())
+}
+
+fn is_dumb(term: Option<&str>) -> bool {
+    term == Some("dumb")
+}
+
+// --- Terminal Width ---
+
+/// Column count of the terminal on `stream`, falling back to the `COLUMNS`
+/// environment variable when the terminal can't be queried.
+pub(crate) fn width(stream: Stream) -> Option<usize> {
+    query_width(stream).or_else(|| columns_env(std::env::var("COLUMNS").ok().as_deref()))
+}
+
+fn columns_env(value: Option<&str>) -> Option<usize> {
+    value?.trim().parse().ok().filter(|&cols| cols > 0)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TIOCGWINSZ: std::os::raw::c_ulong = 0x5413;
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+const TIOCGWINSZ: std::os::raw::c_ulong = 0x40087468;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+fn query_width(stream: Stream) -> Option<usize> {
+    #[repr(C)]
+    struct Winsize {
+        ws_row: u16,
+        ws_col: u16,
+        ws_xpixel: u16,
+        ws_ypixel: u16,
+    }
+    extern "C" {
+        fn ioctl(
+            fd: std::os::raw::c_int,
+            request: std::os::raw::c_ulong,
+            ...
+        ) -> std::os::raw::c_int;
+    }
+    let mut size = Winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let ok = unsafe { ioctl(stream.fd(), TIOCGWINSZ, &mut size as *mut Winsize) } == 0;
+    (ok && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(windows)]
+fn query_width(stream: Stream) -> Option<usize> {
+    #[repr(C)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+    #[repr(C)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+    #[repr(C)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+    extern "system" {
+        fn GetConsoleScreenBufferInfo(
+            handle: *mut std::ffi::c_void,
+            info: *mut ConsoleScreenBufferInfo,
+        ) -> i32;
+    }
+    let mut info: ConsoleScreenBufferInfo = unsafe { std::mem::zeroed() };
+    if unsafe { GetConsoleScreenBufferInfo(stream.handle(), &mut info) } == 0 {
+        return None;
+    }
+    let cols = info.window.right - info.window.left + 1;
+    (cols > 0).then_some(cols as usize)
+}
+
+#[cfg(not(any(
+    windows,
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn query_width(_stream: Stream) -> Option<usize> {
+    None
+}
+
+// --- Cursor ---
+
+pub(crate) const HIDE_CURSOR: &str = "\x1b[?25l";
+pub(crate) const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// Number of bars (or live multi-bar areas) currently hiding the cursor on
+/// stdout and stderr, so the panic hook and Ctrl-C handler know where to
+/// show it again.
+static HIDDEN: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// Record that the cursor on `stream` was hidden or shown again.
+pub(crate) fn track_cursor(stream: Stream, hidden: bool) {
+    let count = &HIDDEN[stream as usize];
+    if hidden {
+        count.fetch_add(1, Ordering::Relaxed);
+    } else {
+        count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .ok();
+    }
+}
+
+/// Number of times the panic hook has shown the cursor on stdout and stderr.
+/// A panic may be caught and the bars carry on, so they compare this with
+/// the value they last saw and hide the cursor again on their next draw.
+static RESTORED: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// How many times the panic hook has shown the cursor on `stream`.
+pub(crate) fn cursor_epoch(stream: Stream) -> usize {
+    RESTORED[stream as usize].load(Ordering::Relaxed)
+}
+
+/// Show the cursor on every stream a bar is hiding it on. `end_line` also
+/// moves past the bar's line, for when the process is going down; a panic
+/// may be caught, so the hook leaves the line alone for the bar to redraw.
+/// Only async-signal-safe calls on unix, since this runs in the SIGINT
+/// handler.
+fn restore_cursor(end_line: bool) {
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        if HIDDEN[stream as usize].load(Ordering::Relaxed) > 0 {
+            RESTORED[stream as usize].fetch_add(1, Ordering::Relaxed);
+            write_raw(stream, SHOW_CURSOR);
+            if end_line {
+                write_raw(stream, "\n");
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn write_raw(stream: Stream, text: &str) {
+    extern "C" {
+        fn write(fd: std::os::raw::c_int, buf: *const std::ffi::c_void, count: usize) -> isize;
+    }
+    unsafe { write(stream.fd(), text.as_ptr().cast(), text.len()) };
+}
+
+#[cfg(not(unix))]
+fn write_raw(stream: Stream, text: &str) {
+    match stream {
+        Stream::Stdout => io::stdout().write_all(text.as_bytes()).ok(),
+        Stream::Stderr => io::stderr().write_all(text.as_bytes()).ok(),
+    };
+}
+
+/// Install, once per process, a panic hook and a SIGINT handler that show
+/// the cursor again before the process goes down. Both chain to whatever
+/// was installed before them.
+pub(crate) fn install_cursor_guard() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_cursor(false);
+            previous(info);
+        }));
+        install_sigint_handler();
+    });
+}
+
+#[cfg(unix)]
+fn install_sigint_handler() {
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+    const SIG_DFL: usize = 0;
+    const SIG_IGN: usize = 1;
+    const SIG_ERR: usize = usize::MAX;
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+        fn raise(sig: c_int) -> c_int;
+    }
+    static PREVIOUS: AtomicUsize = AtomicUsize::new(SIG_DFL);
+
+    extern "C" fn on_sigint(sig: c_int) {
+        restore_cursor(true);
+        match PREVIOUS.load(Ordering::Relaxed) {
+            // Die from the signal as if this handler had never been there.
+            SIG_DFL => unsafe {
+                signal(SIGINT, SIG_DFL);
+                raise(SIGINT);
+            },
+            previous => {
+                let handler: extern "C" fn(c_int) = unsafe { std::mem::transmute(previous) };
+                handler(sig);
+            }
+        }
+    }
+
+    let handler: extern "C" fn(c_int) = on_sigint;
+    match unsafe { signal(SIGINT, handler as usize) } {
+        // Programs that ignore Ctrl-C keep ignoring it.
+        SIG_IGN => unsafe {
+            signal(SIGINT, SIG_IGN);
+        },
+        SIG_ERR => {}
+        previous => PREVIOUS.store(previous, Ordering::Relaxed),
+    }
+}
+
+#[cfg(not(unix))]
+fn install_sigint_handler() {}
+
+// --- Color Support ---
+
+/// How many colors to use on an output, from the environment:
+///
+/// - `CLICOLOR_FORCE` (set and not `0`) enables colors even when piped.
+/// - Otherwise `NO_COLOR` (set and non-empty), `CLICOLOR=0`, `TERM=dumb` or a
+///   non-terminal output disable them.
+/// - `COLORTERM=truecolor` or `24bit` selects truecolor, a `TERM` ending in
+///   `256color` selects the 256-color palette, and anything else gets the
+///   sixteen basic colors.
+pub(crate) fn color_level(is_tty: bool) -> ColorLevel {
+    color_level_from(is_tty, |name| std::env::var(name).ok())
+}
+
+fn color_level_from(is_tty: bool, var: impl Fn(&str) -> Option<String>) -> ColorLevel {
+    let set = |name: &str| var(name).filter(|value| !value.is_empty());
+    let forced = set("CLICOLOR_FORCE").is_some_and(|value| value != "0");
+    let term = var("TERM");
+    let disabled = set("NO_COLOR").is_some()
+        || var("CLICOLOR").as_deref() == Some("0")
+        || is_dumb(term.as_deref())
+        || !is_tty;
+    if disabled && !forced {
+        return ColorLevel::None;
+    }
+    depth(var("COLORTERM").as_deref(), term.as_deref())
+}
+
+/// Color depth advertised by `COLORTERM` and `TERM`, for when colors are
+/// forced on.
+pub(crate) fn color_depth() -> ColorLevel {
+    depth(
+        std::env::var("COLORTERM").ok().as_deref(),
+        std::env::var("TERM").ok().as_deref(),
+    )
+}
+
+fn depth(colorterm: Option<&str>, term: Option<&str>) -> ColorLevel {
+    let term = term.unwrap_or_default();
+    if matches!(colorterm, Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+        ColorLevel::TrueColor
+    } else if term.ends_with("256color") {
+        ColorLevel::Ansi256
+    } else {
+        ColorLevel::Basic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_color_level_follows_tty() {
+        assert_eq!(color_level_from(true, env(&[])), ColorLevel::Basic);
+        assert_eq!(color_level_from(false, env(&[])), ColorLevel::None);
+    }
+
+    #[test]
+    fn test_no_color_and_dumb_terminals_disable_colors() {
+        let level = |vars| color_level_from(true, env(vars));
+        assert_eq!(level(&[("NO_COLOR", "1")]), ColorLevel::None);
+        assert_eq!(level(&[("NO_COLOR", "")]), ColorLevel::Basic);
+        assert_eq!(level(&[("CLICOLOR", "0")]), ColorLevel::None);
+        assert_eq!(level(&[("TERM", "dumb")]), ColorLevel::None);
+    }
+
+    #[test]
+    fn test_clicolor_force_enables_colors() {
+        let level = |vars| color_level_from(false, env(vars));
+        assert_eq!(level(&[("CLICOLOR_FORCE", "1")]), ColorLevel::Basic);
+        assert_eq!(
+            level(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]),
+            ColorLevel::Basic
+        );
+        assert_eq!(level(&[("CLICOLOR_FORCE", "0")]), ColorLevel::None);
+    }
+
+    #[test]
+    fn test_color_depth_detection() {
+        let level = |vars| color_level_from(true, env(vars));
+        assert_eq!(level(&[("COLORTERM", "truecolor")]), ColorLevel::TrueColor);
+        assert_eq!(level(&[("COLORTERM", "24bit")]), ColorLevel::TrueColor);
+        assert_eq!(level(&[("TERM", "xterm-direct")]), ColorLevel::TrueColor);
+        assert_eq!(level(&[("TERM", "xterm-256color")]), ColorLevel::Ansi256);
+        assert_eq!(level(&[("TERM", "xterm")]), ColorLevel::Basic);
+    }
+
+    #[test]
+    fn test_dumb_terminal_detection() {
+        assert!(is_dumb(Some("dumb")));
+        assert!(!is_dumb(Some("xterm")));
+        assert!(!is_dumb(None));
+    }
+
+    #[test]
+    fn test_columns_env_fallback() {
+        assert_eq!(columns_env(Some("120")), Some(120));
+        assert_eq!(columns_env(Some(" 80\n")), Some(80));
+        assert_eq!(columns_env(Some("0")), None);
+        assert_eq!(columns_env(Some("wide")), None);
+        assert_eq!(columns_env(None), None);
+    }
+}
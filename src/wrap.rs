@@ -0,0 +1,230 @@
+//! `io::Read` and `io::Write` adapters that tick a bar per byte.
+
+use std::fs::File;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use crate::{ProgressBar, ProgressBarBuilder, Units};
+
+/// A reader that ticks a [`ProgressBar`] by the number of bytes read.
+///
+/// Buffered reads through [`BufRead`] tick when bytes are consumed, and
+/// seeking is passed through without changing the count.
+///
+/// ```no_run
+/// use nanoprogress::{ProgressBar, ProgressReader};
+/// use std::io;
+///
+/// let bar = ProgressBar::new(1024).message("Reading").start();
+/// let mut reader = ProgressReader::new(io::stdin(), bar);
+/// io::copy(&mut reader, &mut io::sink())?;
+/// reader.bar().success("Done");
+/// # Ok::<(), io::Error>(())
+/// ```
+pub struct ProgressReader<R> {
+    inner: R,
+    bar: ProgressBar,
+}
+
+impl<R> ProgressReader<R> {
+    /// Wrap `inner`, ticking `bar` as bytes are read.
+    pub fn new(inner: R, bar: ProgressBar) -> Self {
+        ProgressReader { inner, bar }
+    }
+
+    /// The bar this reader ticks.
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// A reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// A mutable reference to the wrapped reader. Bytes read through it
+    /// directly are not counted.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the reader, dropping this handle to the bar.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        tick(&self.bar, n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        tick(&self.bar, amt);
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// A writer that ticks a [`ProgressBar`] by the number of bytes written.
+///
+/// ```no_run
+/// use nanoprogress::{ProgressBar, ProgressWriter};
+/// use std::fs::File;
+/// use std::io;
+///
+/// let mut source = File::open("image.iso")?;
+/// let size = source.metadata()?.len();
+/// let bar = ProgressBar::new(size).start();
+/// let mut dest = ProgressWriter::new(File::create("copy.iso")?, bar);
+/// io::copy(&mut source, &mut dest)?;
+/// dest.bar().success("Copied");
+/// # Ok::<(), io::Error>(())
+/// ```
+pub struct ProgressWriter<W> {
+    inner: W,
+    bar: ProgressBar,
+}
+
+impl<W> ProgressWriter<W> {
+    /// Wrap `inner`, ticking `bar` as bytes are written.
+    pub fn new(inner: W, bar: ProgressBar) -> Self {
+        ProgressWriter { inner, bar }
+    }
+
+    /// The bar this writer ticks.
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// A reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// A mutable reference to the wrapped writer. Bytes written through it
+    /// directly are not counted.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the writer, dropping this handle to the bar.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        tick(&self.bar, n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for ProgressWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn tick(bar: &ProgressBar, bytes: usize) {
+    if bytes > 0 {
+        bar.tick(bytes as u64);
+    }
+}
+
+/// A bar counting in bytes, sized to `file`, or a spinner if its size can't
+/// be read.
+pub(crate) fn builder_for(file: &File) -> ProgressBarBuilder {
+    let builder = match file.metadata() {
+        Ok(meta) if meta.is_file() => ProgressBar::new(meta.len()),
+        _ => ProgressBar::spinner(),
+    };
+    builder.units(Units::BinaryBytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_writer;
+    use std::io::Cursor;
+
+    fn counting_bar() -> (crate::tests::TestWriter, ProgressBar) {
+        let (tw, w) = make_writer();
+        let bar = ProgressBar::new(10)
+            .writer(w)
+            .template("{pos}")
+            .unwrap()
+            .start();
+        (tw, bar)
+    }
+
+    #[test]
+    fn test_reader_ticks_bytes_read() {
+        let (tw, bar) = counting_bar();
+        let mut reader = ProgressReader::new(Cursor::new(vec![7u8; 10]), bar);
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 6);
+        assert_eq!(tw.output(), "0\n4\n10\n");
+    }
+
+    #[test]
+    fn test_buf_read_ticks_on_consume() {
+        let (tw, bar) = counting_bar();
+        let mut reader = ProgressReader::new(Cursor::new(b"one\ntwo\n".to_vec()), bar);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "one\n");
+        assert_eq!(tw.output(), "0\n4\n");
+    }
+
+    #[test]
+    fn test_seek_passes_through() {
+        let (_tw, bar) = counting_bar();
+        let mut reader = ProgressReader::new(Cursor::new(b"abcdef".to_vec()), bar);
+        reader.seek(SeekFrom::Start(4)).unwrap();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "ef");
+        assert_eq!(reader.bar().position(), 2);
+    }
+
+    #[test]
+    fn test_writer_ticks_bytes_written() {
+        let (tw, bar) = counting_bar();
+        let mut writer = ProgressWriter::new(Vec::new(), bar);
+        io::copy(&mut Cursor::new(b"hello".to_vec()), &mut writer).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref(), b"hello");
+        assert_eq!(tw.output(), "0\n5\n");
+    }
+
+    #[test]
+    fn test_bar_sized_from_file_metadata() {
+        let path = std::env::temp_dir().join(format!("nanoprogress-wrap-{}", std::process::id()));
+        std::fs::write(&path, [0u8; 1234]).unwrap();
+        let file = File::open(&path).unwrap();
+        assert_eq!(builder_for(&file).total, Some(1234));
+        std::fs::remove_file(&path).unwrap();
+    }
+}